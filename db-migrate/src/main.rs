@@ -1,6 +1,6 @@
 use eyre::{Result, bail};
 use rocksdb::{DB, IteratorMode, Options};
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 use std::path::Path;
 use wincode::{SchemaRead, SchemaWrite, config};
 
@@ -13,18 +13,185 @@ type WincodeConfig = config::Configuration<
     u32,
 >;
 
+const CF_SNOOZE: &str = "snooze";
+const CF_CONSTRAINT: &str = "constraint";
+const CF_PREFERENCE: &str = "preference";
+const CF_PRERELEASE: &str = "prerelease";
+const CF_FILTER: &str = "filter";
+const CF_DELIVERED: &str = "delivered";
+const CF_COLLECTION: &str = "collection";
+const CF_COLLECTION_SUBSCRIPTION: &str = "collection_subscription";
+const CF_FOLLOW: &str = "follow";
+const CF_TAG: &str = "tag";
+const CF_NOTIFICATION_LOG: &str = "notification_log";
+const CF_BOT: &str = "bot";
+const DENYLIST_KEY: &str = "denylist";
+const ANNOUNCED_VERSION_KEY: &str = "announced_version";
+const CF_AUDIT_LOG: &str = "audit_log";
+const COLUMN_FAMILIES: [&str; 13] = [
+    CF_SNOOZE,
+    CF_CONSTRAINT,
+    CF_PREFERENCE,
+    CF_PRERELEASE,
+    CF_FILTER,
+    CF_DELIVERED,
+    CF_COLLECTION,
+    CF_COLLECTION_SUBSCRIPTION,
+    CF_FOLLOW,
+    CF_TAG,
+    CF_NOTIFICATION_LOG,
+    CF_BOT,
+    CF_AUDIT_LOG,
+];
+
 #[derive(SchemaRead, SchemaWrite, Debug, Clone)]
 struct User {
     packages: Vec<String>,
     notification_enabled: bool,
 }
 
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct Snooze {
+    package: String,
+    after: String,
+    until: Option<u64>,
+}
+
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct Delivered {
+    package: String,
+    after: String,
+}
+
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct PackageConstraint {
+    package: String,
+    constraint: String,
+}
+
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct PreferenceValue {
+    key: String,
+    value: String,
+}
+
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct PrereleaseOverride {
+    package: String,
+    show: bool,
+}
+
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct SavedFilter {
+    name: String,
+    expression: String,
+}
+
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct Tag {
+    name: String,
+    packages: Vec<String>,
+}
+
+#[derive(SchemaRead, SchemaWrite, Debug, Clone)]
+struct LoggedNotification {
+    event_id: String,
+    packages: Vec<String>,
+    sent: u64,
+}
+
+/// Create the tables of the SQLite backend, so that databases created by older versions can be
+/// migrated as well.
+fn init_sqlite(sqlite_db: &Connection) -> Result<()> {
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS subscription ( user_id TEXT NOT NULL, package TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS notification ( user_id TEXT )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS snooze ( user_id TEXT NOT NULL, package TEXT NOT NULL, after TEXT NOT NULL, until INTEGER )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS delivered ( user_id TEXT NOT NULL, package TEXT NOT NULL, after TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS subscription_constraint ( user_id TEXT NOT NULL, package TEXT NOT NULL, constraint_spec TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS preference ( user_id TEXT NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS prerelease_override ( user_id TEXT NOT NULL, package TEXT NOT NULL, show INTEGER NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS saved_filter ( user_id TEXT NOT NULL, name TEXT NOT NULL, expression TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS collection ( name TEXT NOT NULL, package TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS collection_subscription ( user_id TEXT NOT NULL, collection TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS follow ( user_id TEXT NOT NULL, followed TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS package_tag ( user_id TEXT NOT NULL, tag TEXT NOT NULL, package TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS notification_log ( user_id TEXT NOT NULL, event_id TEXT NOT NULL, package TEXT NOT NULL, sent INTEGER NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS denylist ( entry TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS announced_version ( version TEXT NOT NULL )",
+        (),
+    )?;
+    sqlite_db.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log ( time INTEGER NOT NULL, admin TEXT NOT NULL, command TEXT NOT NULL )",
+        (),
+    )?;
+    Ok(())
+}
+
+fn distinct_users(sqlite_db: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = sqlite_db.prepare(&format!("SELECT DISTINCT user_id FROM {table}"))?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    let mut users: Vec<String> = Vec::new();
+    for row in rows {
+        users.push(row?);
+    }
+    Ok(users)
+}
+
+fn open_rocksdb(path: &Path) -> Result<DB> {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    Ok(DB::open_cf(&opts, path.join("anicca"), COLUMN_FAMILIES)?)
+}
+
 fn sqlite_to_rocksdb<T: AsRef<Path>>(path: T) -> Result<()> {
     let path = path.as_ref();
     let sqlite_db = Connection::open(path.join("anicca.db"))?;
-    let mut opts = Options::default();
-    opts.create_if_missing(true);
-    let rocksdb_db = DB::open(&opts, path.join("anicca"))?;
+    init_sqlite(&sqlite_db)?;
+    let rocksdb_db = open_rocksdb(path)?;
     let wincode_config = config::Configuration::default()
         .disable_preallocation_size_limit()
         .with_varint_encoding();
@@ -60,27 +227,268 @@ fn sqlite_to_rocksdb<T: AsRef<Path>>(path: T) -> Result<()> {
         rocksdb_db.put(user_id.as_bytes(), encoded)?;
     }
 
+    let snooze_cf = rocksdb_db.cf_handle(CF_SNOOZE).unwrap();
+    for user_id in distinct_users(&sqlite_db, "snooze")? {
+        let mut stmt =
+            sqlite_db.prepare("SELECT package, after, until FROM snooze WHERE user_id = ?1")?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok(Snooze {
+                package: row.get(0)?,
+                after: row.get(1)?,
+                until: row.get::<_, Option<i64>>(2)?.map(|until| until as u64),
+            })
+        })?;
+        let mut snoozes: Vec<Snooze> = Vec::new();
+        for row in rows {
+            snoozes.push(row?);
+        }
+        let encoded = config::serialize(&snoozes, wincode_config)?;
+        rocksdb_db.put_cf(snooze_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let constraint_cf = rocksdb_db.cf_handle(CF_CONSTRAINT).unwrap();
+    for user_id in distinct_users(&sqlite_db, "subscription_constraint")? {
+        let mut stmt = sqlite_db.prepare(
+            "SELECT package, constraint_spec FROM subscription_constraint WHERE user_id = ?1",
+        )?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok(PackageConstraint {
+                package: row.get(0)?,
+                constraint: row.get(1)?,
+            })
+        })?;
+        let mut constraints: Vec<PackageConstraint> = Vec::new();
+        for row in rows {
+            constraints.push(row?);
+        }
+        let encoded = config::serialize(&constraints, wincode_config)?;
+        rocksdb_db.put_cf(constraint_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let preference_cf = rocksdb_db.cf_handle(CF_PREFERENCE).unwrap();
+    for user_id in distinct_users(&sqlite_db, "preference")? {
+        let mut stmt = sqlite_db.prepare("SELECT key, value FROM preference WHERE user_id = ?1")?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok(PreferenceValue {
+                key: row.get(0)?,
+                value: row.get(1)?,
+            })
+        })?;
+        let mut preferences: Vec<PreferenceValue> = Vec::new();
+        for row in rows {
+            preferences.push(row?);
+        }
+        let encoded = config::serialize(&preferences, wincode_config)?;
+        rocksdb_db.put_cf(preference_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let prerelease_cf = rocksdb_db.cf_handle(CF_PRERELEASE).unwrap();
+    for user_id in distinct_users(&sqlite_db, "prerelease_override")? {
+        let mut stmt = sqlite_db
+            .prepare("SELECT package, show FROM prerelease_override WHERE user_id = ?1")?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok(PrereleaseOverride {
+                package: row.get(0)?,
+                show: row.get(1)?,
+            })
+        })?;
+        let mut overrides: Vec<PrereleaseOverride> = Vec::new();
+        for row in rows {
+            overrides.push(row?);
+        }
+        let encoded = config::serialize(&overrides, wincode_config)?;
+        rocksdb_db.put_cf(prerelease_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let filter_cf = rocksdb_db.cf_handle(CF_FILTER).unwrap();
+    for user_id in distinct_users(&sqlite_db, "saved_filter")? {
+        let mut stmt =
+            sqlite_db.prepare("SELECT name, expression FROM saved_filter WHERE user_id = ?1")?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok(SavedFilter {
+                name: row.get(0)?,
+                expression: row.get(1)?,
+            })
+        })?;
+        let mut filters: Vec<SavedFilter> = Vec::new();
+        for row in rows {
+            filters.push(row?);
+        }
+        let encoded = config::serialize(&filters, wincode_config)?;
+        rocksdb_db.put_cf(filter_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let delivered_cf = rocksdb_db.cf_handle(CF_DELIVERED).unwrap();
+    for user_id in distinct_users(&sqlite_db, "delivered")? {
+        let mut stmt =
+            sqlite_db.prepare("SELECT package, after FROM delivered WHERE user_id = ?1")?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok(Delivered {
+                package: row.get(0)?,
+                after: row.get(1)?,
+            })
+        })?;
+        let mut delivered: Vec<Delivered> = Vec::new();
+        for row in rows {
+            delivered.push(row?);
+        }
+        let encoded = config::serialize(&delivered, wincode_config)?;
+        rocksdb_db.put_cf(delivered_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let collection_cf = rocksdb_db.cf_handle(CF_COLLECTION).unwrap();
+    let mut stmt = sqlite_db.prepare("SELECT DISTINCT name FROM collection")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    let mut names: Vec<String> = Vec::new();
+    for row in rows {
+        names.push(row?);
+    }
+    for name in names {
+        let mut stmt = sqlite_db.prepare("SELECT package FROM collection WHERE name = ?1")?;
+        let rows = stmt.query_map([&name], |row| row.get(0))?;
+        let mut packages: Vec<String> = Vec::new();
+        for row in rows {
+            packages.push(row?);
+        }
+        let encoded = config::serialize(&packages, wincode_config)?;
+        rocksdb_db.put_cf(collection_cf, name.as_bytes(), encoded)?;
+    }
+
+    let collection_subscription_cf = rocksdb_db.cf_handle(CF_COLLECTION_SUBSCRIPTION).unwrap();
+    for user_id in distinct_users(&sqlite_db, "collection_subscription")? {
+        let mut stmt = sqlite_db
+            .prepare("SELECT collection FROM collection_subscription WHERE user_id = ?1")?;
+        let rows = stmt.query_map([&user_id], |row| row.get(0))?;
+        let mut collections: Vec<String> = Vec::new();
+        for row in rows {
+            collections.push(row?);
+        }
+        let encoded = config::serialize(&collections, wincode_config)?;
+        rocksdb_db.put_cf(collection_subscription_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let follow_cf = rocksdb_db.cf_handle(CF_FOLLOW).unwrap();
+    for user_id in distinct_users(&sqlite_db, "follow")? {
+        let mut stmt = sqlite_db.prepare("SELECT followed FROM follow WHERE user_id = ?1")?;
+        let rows = stmt.query_map([&user_id], |row| row.get(0))?;
+        let mut followed: Vec<String> = Vec::new();
+        for row in rows {
+            followed.push(row?);
+        }
+        let encoded = config::serialize(&followed, wincode_config)?;
+        rocksdb_db.put_cf(follow_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let tag_cf = rocksdb_db.cf_handle(CF_TAG).unwrap();
+    for user_id in distinct_users(&sqlite_db, "package_tag")? {
+        let mut stmt = sqlite_db.prepare(
+            "SELECT tag, package FROM package_tag WHERE user_id = ?1 ORDER BY tag, package",
+        )?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut tags: Vec<Tag> = Vec::new();
+        for row in rows {
+            let (name, package) = row?;
+            match tags.last_mut() {
+                Some(tag) if tag.name == name => tag.packages.push(package),
+                _ => tags.push(Tag {
+                    name,
+                    packages: vec![package],
+                }),
+            }
+        }
+        let encoded = config::serialize(&tags, wincode_config)?;
+        rocksdb_db.put_cf(tag_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let notification_log_cf = rocksdb_db.cf_handle(CF_NOTIFICATION_LOG).unwrap();
+    for user_id in distinct_users(&sqlite_db, "notification_log")? {
+        let mut stmt = sqlite_db.prepare(
+            "SELECT event_id, package, sent FROM notification_log WHERE user_id = ?1 ORDER BY sent, event_id, package",
+        )?;
+        let rows = stmt.query_map([&user_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })?;
+        let mut log: Vec<LoggedNotification> = Vec::new();
+        for row in rows {
+            let (event_id, package, sent) = row?;
+            match log.last_mut() {
+                Some(notification) if notification.event_id == event_id => {
+                    notification.packages.push(package)
+                }
+                _ => log.push(LoggedNotification {
+                    event_id,
+                    packages: vec![package],
+                    sent,
+                }),
+            }
+        }
+        let encoded = config::serialize(&log, wincode_config)?;
+        rocksdb_db.put_cf(notification_log_cf, user_id.as_bytes(), encoded)?;
+    }
+
+    let bot_cf = rocksdb_db.cf_handle(CF_BOT).unwrap();
+    let mut stmt = sqlite_db.prepare("SELECT entry FROM denylist")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    let mut denylist: Vec<String> = Vec::new();
+    for row in rows {
+        denylist.push(row?);
+    }
+    if !denylist.is_empty() {
+        let encoded = config::serialize(&denylist, wincode_config)?;
+        rocksdb_db.put_cf(bot_cf, DENYLIST_KEY.as_bytes(), encoded)?;
+    }
+    let version = sqlite_db
+        .query_row("SELECT version FROM announced_version", [], |row| {
+            row.get::<_, String>(0)
+        })
+        .optional()?;
+    if let Some(version) = version {
+        let encoded = config::serialize(&vec![version], wincode_config)?;
+        rocksdb_db.put_cf(bot_cf, ANNOUNCED_VERSION_KEY.as_bytes(), encoded)?;
+    }
+
+    let audit_log_cf = rocksdb_db.cf_handle(CF_AUDIT_LOG).unwrap();
+    let mut stmt =
+        sqlite_db.prepare("SELECT time, admin, command FROM audit_log ORDER BY rowid")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)? as u64,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut audit_log: Vec<(String, Vec<String>)> = Vec::new();
+    for row in rows {
+        let (time, admin, command) = row?;
+        let key = format!("{time:020}-{admin}");
+        match audit_log.iter_mut().find(|(old, _)| *old == key) {
+            Some((_, commands)) => commands.push(command),
+            None => audit_log.push((key, vec![command])),
+        }
+    }
+    for (key, commands) in audit_log {
+        let encoded = config::serialize(&commands, wincode_config)?;
+        rocksdb_db.put_cf(audit_log_cf, key.as_bytes(), encoded)?;
+    }
+
     Ok(())
 }
 
 fn rocksdb_to_sqlite<T: AsRef<Path>>(path: T) -> Result<()> {
     let path = path.as_ref();
     let sqlite_db = Connection::open(path.join("anicca.db"))?;
-    let mut opts = Options::default();
-    opts.create_if_missing(true);
-    let rocksdb_db = DB::open(&opts, path.join("anicca"))?;
+    let rocksdb_db = open_rocksdb(path)?;
     let wincode_config = config::Configuration::default()
         .disable_preallocation_size_limit()
         .with_varint_encoding();
 
-    sqlite_db.execute(
-        "CREATE TABLE IF NOT EXISTS subscription ( user_id TEXT NOT NULL, package TEXT NOT NULL )",
-        (),
-    )?;
-    sqlite_db.execute(
-        "CREATE TABLE IF NOT EXISTS notification ( user_id TEXT )",
-        (),
-    )?;
+    init_sqlite(&sqlite_db)?;
 
     let iter = rocksdb_db.iterator(IteratorMode::Start);
     for item in iter {
@@ -99,6 +507,206 @@ fn rocksdb_to_sqlite<T: AsRef<Path>>(path: T) -> Result<()> {
         }
     }
 
+    let snooze_cf = rocksdb_db.cf_handle(CF_SNOOZE).unwrap();
+    for item in rocksdb_db.iterator_cf(snooze_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let snoozes = config::deserialize::<Vec<Snooze>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db.prepare(
+            "INSERT INTO snooze (user_id, package, after, until) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for snooze in snoozes {
+            stmt.execute((
+                user_id,
+                &snooze.package,
+                &snooze.after,
+                snooze.until.map(|until| until as i64),
+            ))?;
+        }
+    }
+
+    let constraint_cf = rocksdb_db.cf_handle(CF_CONSTRAINT).unwrap();
+    for item in rocksdb_db.iterator_cf(constraint_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let constraints =
+            config::deserialize::<Vec<PackageConstraint>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db.prepare(
+            "INSERT INTO subscription_constraint (user_id, package, constraint_spec) VALUES (?1, ?2, ?3)",
+        )?;
+        for constraint in constraints {
+            stmt.execute([
+                user_id,
+                constraint.package.as_str(),
+                constraint.constraint.as_str(),
+            ])?;
+        }
+    }
+
+    let preference_cf = rocksdb_db.cf_handle(CF_PREFERENCE).unwrap();
+    for item in rocksdb_db.iterator_cf(preference_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let preferences =
+            config::deserialize::<Vec<PreferenceValue>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db
+            .prepare("INSERT INTO preference (user_id, key, value) VALUES (?1, ?2, ?3)")?;
+        for preference in preferences {
+            stmt.execute([user_id, preference.key.as_str(), preference.value.as_str()])?;
+        }
+    }
+
+    let prerelease_cf = rocksdb_db.cf_handle(CF_PRERELEASE).unwrap();
+    for item in rocksdb_db.iterator_cf(prerelease_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let overrides =
+            config::deserialize::<Vec<PrereleaseOverride>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db.prepare(
+            "INSERT INTO prerelease_override (user_id, package, show) VALUES (?1, ?2, ?3)",
+        )?;
+        for item in overrides {
+            stmt.execute((user_id, &item.package, item.show))?;
+        }
+    }
+
+    let filter_cf = rocksdb_db.cf_handle(CF_FILTER).unwrap();
+    for item in rocksdb_db.iterator_cf(filter_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let filters = config::deserialize::<Vec<SavedFilter>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db
+            .prepare("INSERT INTO saved_filter (user_id, name, expression) VALUES (?1, ?2, ?3)")?;
+        for filter in filters {
+            stmt.execute([user_id, filter.name.as_str(), filter.expression.as_str()])?;
+        }
+    }
+
+    let delivered_cf = rocksdb_db.cf_handle(CF_DELIVERED).unwrap();
+    for item in rocksdb_db.iterator_cf(delivered_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let delivered = config::deserialize::<Vec<Delivered>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db
+            .prepare("INSERT INTO delivered (user_id, package, after) VALUES (?1, ?2, ?3)")?;
+        for item in delivered {
+            stmt.execute([user_id, item.package.as_str(), item.after.as_str()])?;
+        }
+    }
+
+    let collection_cf = rocksdb_db.cf_handle(CF_COLLECTION).unwrap();
+    for item in rocksdb_db.iterator_cf(collection_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let name = str::from_utf8(&key)?;
+        let packages = config::deserialize::<Vec<String>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt =
+            sqlite_db.prepare("INSERT INTO collection (name, package) VALUES (?1, ?2)")?;
+        for package in packages {
+            stmt.execute([name, package.as_str()])?;
+        }
+    }
+
+    let collection_subscription_cf = rocksdb_db.cf_handle(CF_COLLECTION_SUBSCRIPTION).unwrap();
+    for item in rocksdb_db.iterator_cf(collection_subscription_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let collections = config::deserialize::<Vec<String>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db
+            .prepare("INSERT INTO collection_subscription (user_id, collection) VALUES (?1, ?2)")?;
+        for collection in collections {
+            stmt.execute([user_id, collection.as_str()])?;
+        }
+    }
+
+    let follow_cf = rocksdb_db.cf_handle(CF_FOLLOW).unwrap();
+    for item in rocksdb_db.iterator_cf(follow_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let followed = config::deserialize::<Vec<String>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt =
+            sqlite_db.prepare("INSERT INTO follow (user_id, followed) VALUES (?1, ?2)")?;
+        for followed in followed {
+            stmt.execute([user_id, followed.as_str()])?;
+        }
+    }
+
+    let tag_cf = rocksdb_db.cf_handle(CF_TAG).unwrap();
+    for item in rocksdb_db.iterator_cf(tag_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let tags = config::deserialize::<Vec<Tag>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db
+            .prepare("INSERT INTO package_tag (user_id, tag, package) VALUES (?1, ?2, ?3)")?;
+        for tag in tags {
+            for package in tag.packages {
+                stmt.execute([user_id, tag.name.as_str(), package.as_str()])?;
+            }
+        }
+    }
+
+    let notification_log_cf = rocksdb_db.cf_handle(CF_NOTIFICATION_LOG).unwrap();
+    for item in rocksdb_db.iterator_cf(notification_log_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let user_id = str::from_utf8(&key)?;
+        let log =
+            config::deserialize::<Vec<LoggedNotification>, WincodeConfig>(&val, wincode_config)?;
+
+        let mut stmt = sqlite_db.prepare(
+            "INSERT INTO notification_log (user_id, event_id, package, sent) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for notification in log {
+            for package in notification.packages {
+                stmt.execute((
+                    user_id,
+                    notification.event_id.as_str(),
+                    package.as_str(),
+                    notification.sent as i64,
+                ))?;
+            }
+        }
+    }
+
+    let bot_cf = rocksdb_db.cf_handle(CF_BOT).unwrap();
+    if let Some(val) = rocksdb_db.get_cf(bot_cf, DENYLIST_KEY.as_bytes())? {
+        let denylist = config::deserialize::<Vec<String>, WincodeConfig>(&val, wincode_config)?;
+        let mut stmt = sqlite_db.prepare("INSERT INTO denylist (entry) VALUES (?1)")?;
+        for entry in denylist {
+            stmt.execute([entry.as_str()])?;
+        }
+    }
+    if let Some(val) = rocksdb_db.get_cf(bot_cf, ANNOUNCED_VERSION_KEY.as_bytes())? {
+        let versions = config::deserialize::<Vec<String>, WincodeConfig>(&val, wincode_config)?;
+        if let Some(version) = versions.last() {
+            sqlite_db.execute(
+                "INSERT INTO announced_version (version) VALUES (?1)",
+                [version.as_str()],
+            )?;
+        }
+    }
+
+    let audit_log_cf = rocksdb_db.cf_handle(CF_AUDIT_LOG).unwrap();
+    let mut stmt =
+        sqlite_db.prepare("INSERT INTO audit_log (time, admin, command) VALUES (?1, ?2, ?3)")?;
+    for item in rocksdb_db.iterator_cf(audit_log_cf, IteratorMode::Start) {
+        let (key, val) = item?;
+        let (time, admin) = str::from_utf8(&key)?.split_at(20);
+        let time = time.parse::<u64>()?;
+        let commands = config::deserialize::<Vec<String>, WincodeConfig>(&val, wincode_config)?;
+        for command in commands {
+            stmt.execute((time as i64, &admin[1..], command.as_str()))?;
+        }
+    }
+
     Ok(())
 }
 
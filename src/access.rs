@@ -0,0 +1,106 @@
+//! Who may invite the bot and run commands, by user id and server name.
+
+use matrix_sdk::ruma::{UserId, events::room::message::RoomMessageEventContent};
+use tracing::{info, warn};
+
+use crate::{
+    bot::user_lang,
+    config::Config,
+    db::{Database, DatabaseImpl},
+    i18n::t,
+};
+
+/// Whether `user_id` may use the bot, given the `denylist` the admins keep on top of the config.
+pub fn is_allowed(config: &Config, denylist: &[String], user_id: &UserId) -> bool {
+    if config.is_admin(user_id) {
+        return true;
+    }
+    let access = &config.access;
+    let server = user_id.server_name();
+    let denied = access.deny_users.iter().any(|id| id == user_id)
+        || access.deny_servers.iter().any(|name| name == server)
+        || denylist
+            .iter()
+            .any(|entry| entry == user_id.as_str() || entry == server.as_str());
+    if denied {
+        return false;
+    }
+    (access.allow_users.is_empty() && access.allow_servers.is_empty())
+        || access.allow_users.iter().any(|id| id == user_id)
+        || access.allow_servers.iter().any(|name| name == server)
+}
+
+/// Whether `user_id` may use the bot, also checking the denylist in the database. Only admins get in while the
+/// denylist cannot be read.
+pub async fn check(config: &Config, db: &DatabaseImpl, user_id: &UserId) -> bool {
+    let denylist = match db.get_denylist().await {
+        Ok(denylist) => denylist,
+        Err(e) => {
+            warn!("Unable to read the denylist: {}", e);
+            return config.is_admin(user_id);
+        }
+    };
+    is_allowed(config, &denylist, user_id)
+}
+
+/// Check a command of `user_id` against the access policy. `Err` holds what to reply instead of running the command,
+/// if anything.
+pub async fn check_command(
+    config: &Config,
+    db: &DatabaseImpl,
+    user_id: &UserId,
+) -> Result<(), Option<RoomMessageEventContent>> {
+    if check(config, db, user_id).await {
+        return Ok(());
+    }
+    info!("Ignoring command from {}: Access denied.", user_id);
+    if !config.access.reply_denied {
+        return Err(None);
+    }
+    let lang = user_lang(db, user_id).await.unwrap_or_default();
+    Err(Some(RoomMessageEventContent::notice_plain(t!(
+        lang,
+        "access.denied"
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Access;
+    use matrix_sdk::ruma::{OwnedServerName, OwnedUserId, ServerName};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_is_allowed() {
+        let user = |id: &str| -> OwnedUserId { UserId::parse(id).unwrap() };
+        let server = |name: &str| -> OwnedServerName { ServerName::parse(name).unwrap() };
+        let mut config = Config {
+            admin_ids: Arc::new([user("@admin:elsewhere.org")]),
+            ..Default::default()
+        };
+        assert!(is_allowed(&config, &[], &user("@alice:example.org")));
+        assert!(!is_allowed(
+            &config,
+            &["example.org".to_owned()],
+            &user("@alice:example.org")
+        ));
+        assert!(!is_allowed(
+            &config,
+            &["@alice:example.org".to_owned()],
+            &user("@alice:example.org")
+        ));
+
+        config.access = Access {
+            allow_servers: Arc::new([server("example.org")]),
+            allow_users: Arc::new([user("@bob:elsewhere.org")]),
+            deny_users: Arc::new([user("@spam:example.org")]),
+            ..Default::default()
+        };
+        assert!(is_allowed(&config, &[], &user("@alice:example.org")));
+        assert!(is_allowed(&config, &[], &user("@bob:elsewhere.org")));
+        assert!(is_allowed(&config, &[], &user("@admin:elsewhere.org")));
+        assert!(!is_allowed(&config, &[], &user("@carol:elsewhere.org")));
+        assert!(!is_allowed(&config, &[], &user("@spam:example.org")));
+    }
+}
@@ -1,9 +1,11 @@
 use eyre::Result;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::{fmt, path::Path};
 use tokio::fs;
 
+use crate::{duration, filter::Filter, history::History, search, version::Constraint};
+
 const PKGSUPDATE_JSON_URL: &str =
     "https://raw.githubusercontent.com/AOSC-Dev/anicca/main/pkgsupdate.json";
 const PKGSUPDATE_JSON_FILENAME_DIFF: &str = "anicca_diff.json";
@@ -21,6 +23,32 @@ pub struct Package {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Anicca(Vec<Package>);
 
+/// A subscribed package, optionally limited to the updates satisfying a constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    pub package: String,
+    pub constraint: Option<Constraint>,
+}
+
+impl Subscription {
+    pub fn matches(&self, package: &Package) -> bool {
+        self.package == package.name
+            && self
+                .constraint
+                .as_ref()
+                .is_none_or(|constraint| constraint.matches(&package.before, &package.after))
+    }
+}
+
+impl fmt::Display for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.constraint {
+            Some(constraint) => write!(f, "{}{}", self.package, constraint),
+            None => f.write_str(&self.package),
+        }
+    }
+}
+
 impl Anicca {
     pub async fn fetch_json(data_dir: &Path) -> Result<()> {
         let file_path = data_dir.join(PKGSUPDATE_JSON_FILENAME);
@@ -34,8 +62,8 @@ impl Anicca {
             .bytes()
             .await?;
 
+        let current_data = serde_json::from_slice::<Anicca>(&content)?;
         if file_path.exists() {
-            let current_data = serde_json::from_slice::<Anicca>(&content)?;
             let past_data = serde_json::from_slice::<Anicca>(&fs::read(&file_path).await?)?;
             let diff = current_data.diff(&past_data);
             fs::write(&diff_path, serde_json::to_string(&diff.0)?).await?;
@@ -45,6 +73,10 @@ impl Anicca {
             fs::copy(&file_path, &diff_path).await?;
         }
 
+        let mut history = History::load(data_dir).await?;
+        history.record(&current_data, duration::now());
+        history.save(data_dir).await?;
+
         Ok(())
     }
 
@@ -66,6 +98,10 @@ impl Anicca {
         Ok(serde_json::from_slice(&content)?)
     }
 
+    pub fn packages(&self) -> &[Package] {
+        &self.0
+    }
+
     pub fn get_updates(data: &Self, packages: &[String]) -> Result<Vec<Package>> {
         let updates = data
             .0
@@ -77,8 +113,37 @@ impl Anicca {
         Ok(updates)
     }
 
-    pub fn get_subscription_updates(&self, packages: &[String]) -> Result<Vec<Package>> {
-        Self::get_updates(self, packages)
+    pub fn get_subscription_updates(&self, subscriptions: &[Subscription]) -> Result<Vec<Package>> {
+        let updates = self
+            .0
+            .iter()
+            .filter(|pkg| subscriptions.iter().any(|sub| sub.matches(pkg)))
+            .cloned()
+            .collect::<Vec<Package>>();
+
+        Ok(updates)
+    }
+
+    pub fn get_filter_updates(&self, filters: &[Filter]) -> Result<Vec<Package>> {
+        let updates = self
+            .0
+            .iter()
+            .filter(|pkg| filters.iter().any(|filter| filter.matches(pkg)))
+            .cloned()
+            .collect::<Vec<Package>>();
+
+        Ok(updates)
+    }
+
+    /// Packages whose name or path matches `query`, best matches first.
+    pub fn search(&self, query: &str) -> Vec<&Package> {
+        let mut matches = self
+            .0
+            .iter()
+            .filter_map(|pkg| search::rank(query, &pkg.name, &pkg.path).map(|kind| (kind, pkg)))
+            .collect::<Vec<_>>();
+        matches.sort_by(|a, b| search::compare((a.0, &a.1.name), (b.0, &b.1.name)));
+        matches.into_iter().map(|(_, pkg)| pkg).collect()
     }
 
     fn diff(&self, past_data: &Self) -> Self {
@@ -1,83 +1,448 @@
 use eyre::Result;
 use matrix_sdk::{
     Client, Room,
-    ruma::{UserId, events::room::message::RoomMessageEventContent},
+    ruma::{
+        OwnedEventId, OwnedUserId, UserId,
+        events::room::message::{FormattedBody, RoomMessageEventContent},
+    },
 };
-use std::path::Path;
-use tracing::info;
+use std::{path::Path, time::Duration};
+use tracing::{info, warn};
 
-use crate::db::*;
-use anicca_subscribe::anicca::{Anicca, Package};
+use crate::{
+    command::view::{Item, View},
+    db::*,
+    i18n::{Lang, t, tn},
+};
+use anicca_subscribe::{
+    anicca::{Anicca, Package, Subscription},
+    changelog::Changelog,
+    duration,
+    filter::Filter,
+    history::History,
+    version::Version,
+};
+
+pub const CHANGELOG: &str = include_str!("../CHANGELOG.md");
+
+/// How long a sent notification can still be replied to with a command.
+const NOTIFICATION_LOG_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+pub fn format_update_packages(lang: Lang, packages: &[Package]) -> (String, String) {
+    format_updates(lang, packages, &[], &View::all(), None)
+}
+
+/// Render updates as [`View`] says, with their pending time taken from `history` if given.
+pub fn format_updates(
+    lang: Lang,
+    packages: &[Package],
+    tags: &[Tag],
+    view: &View,
+    history: Option<&History>,
+) -> (String, String) {
+    let header = t!(
+        lang,
+        "updates.found",
+        updates = tn!(lang, "count.update", packages.len())
+    );
+    let items = packages
+        .iter()
+        .map(|pkg| Item::update(pkg, history))
+        .collect();
+    view.render(lang, &header, items, tags)
+}
 
-pub fn format_update_packages(packages: &mut [Package]) -> (String, String) {
-    packages.sort_by(|a, b| a.name.cmp(&b.name));
-    let packages_list = packages
+/// Split `items` into one group per tag, in the order of `tags`, followed by the items without any tag. Empty groups
+/// are left out.
+pub fn group_by_tag<'a, T>(
+    items: &'a [T],
+    package: impl Fn(&T) -> &str,
+    tags: &'a [Tag],
+) -> Vec<(Option<&'a str>, Vec<&'a T>)> {
+    let mut groups = Vec::new();
+    for tag in tags {
+        let tagged = items
+            .iter()
+            .filter(|item| tag.packages.iter().any(|name| name == package(item)))
+            .collect::<Vec<&T>>();
+        if !tagged.is_empty() {
+            groups.push((Some(tag.name.as_str()), tagged));
+        }
+    }
+    let untagged = items
         .iter()
+        .filter(|item| {
+            !tags
+                .iter()
+                .any(|tag| tag.packages.iter().any(|name| name == package(item)))
+        })
+        .collect::<Vec<&T>>();
+    if !untagged.is_empty() {
+        groups.push((None, untagged));
+    }
+    groups
+}
+
+/// The tags whose packages the user does not want to be notified about.
+pub async fn muted_tags(db: &DatabaseImpl, user_id: &UserId) -> Result<Vec<String>> {
+    Ok(db
+        .get_preference(user_id, Preference::MutedTags)
+        .await?
+        .map(|value| value.split(',').map(|tag| tag.to_owned()).collect())
+        .unwrap_or_default())
+}
+
+pub async fn set_muted_tags(db: &DatabaseImpl, user_id: &UserId, tags: Vec<String>) -> Result<()> {
+    db.set_preference(
+        user_id,
+        Preference::MutedTags,
+        (!tags.is_empty()).then(|| tags.join(",")),
+    )
+    .await
+}
+
+/// The language the user chose for bot replies.
+pub async fn user_lang(db: &DatabaseImpl, user_id: &UserId) -> Result<Lang> {
+    Ok(db
+        .get_preference(user_id, Preference::Language)
+        .await?
+        .and_then(|code| code.parse().ok())
+        .unwrap_or_default())
+}
+
+/// The user's subscriptions together with their version constraints.
+pub async fn user_subscriptions(db: &DatabaseImpl, user_id: &UserId) -> Result<Vec<Subscription>> {
+    let packages = db.get_packages(user_id).await?;
+    let constraints = db.get_constraints(user_id).await?;
+    Ok(packages
+        .into_iter()
         .map(|package| {
-            format!(
-                "<li><code>{}: {} -> {}</code>{}</li>",
-                package.name,
-                package.before,
-                package.after,
-                if package.warnings.len() > 1 {
-                    format!(" ({})", &package.warnings[1])
-                } else {
-                    String::new()
-                }
-            )
+            let constraint =
+                constraints
+                    .iter()
+                    .find(|c| c.package == package)
+                    .and_then(|c| match c.constraint.parse() {
+                        Ok(constraint) => Some(constraint),
+                        Err(e) => {
+                            warn!("Ignoring constraint of {} for {}: {}", package, user_id, e);
+                            None
+                        }
+                    });
+            Subscription {
+                package,
+                constraint,
+            }
         })
-        .collect::<String>();
-    let html_output = format!(
-        "<strong>Found {} update{}</strong><br/><ul>{}</ul>",
-        packages.len(),
-        if packages.len() >= 2 { "s" } else { "" },
-        packages_list
-    );
-    let plain_output = html_output
-        .replace("<code>", "")
-        .replace("</code>", "")
-        .replace("<br/>", "\n")
-        .replace("<ul>", "")
-        .replace("</ul>", "")
-        .replace("<li>", "- ")
-        .replace("</li>", "\n")
-        .replace("<strong>", "")
-        .replace("</strong>", ": ");
-    (plain_output, html_output)
-}
-
-async fn dm_or_create(client: Client, user_id: &UserId) -> Result<Room> {
+        .collect())
+}
+
+/// The packages of the collections the user follows, which count as subscriptions without a constraint.
+pub async fn user_collection_packages(db: &DatabaseImpl, user_id: &UserId) -> Result<Vec<String>> {
+    let names = db.get_collection_subscriptions(user_id).await?;
+    Ok(db
+        .get_collections()
+        .await?
+        .into_iter()
+        .filter(|collection| names.contains(&collection.name))
+        .flat_map(|collection| collection.packages)
+        .collect())
+}
+
+pub async fn is_list_public(db: &DatabaseImpl, user_id: &UserId) -> Result<bool> {
+    Ok(db
+        .get_preference(user_id, Preference::PublicList)
+        .await?
+        .is_some_and(|value| value == "true"))
+}
+
+/// Everything the user is subscribed to: their own subscriptions first, then the packages of the collections they
+/// follow and the subscriptions of the users they follow, as long as those lists stay public.
+pub async fn effective_subscriptions(
+    db: &DatabaseImpl,
+    user_id: &UserId,
+) -> Result<Vec<Subscription>> {
+    let mut subscriptions = user_subscriptions(db, user_id).await?;
+    let mut extra = user_collection_packages(db, user_id)
+        .await?
+        .into_iter()
+        .map(|package| Subscription {
+            package,
+            constraint: None,
+        })
+        .collect::<Vec<Subscription>>();
+    for followed in db.get_follows(user_id).await? {
+        if is_list_public(db, &followed).await? {
+            extra.extend(user_subscriptions(db, &followed).await?);
+        }
+    }
+    for sub in extra {
+        if !subscriptions.iter().any(|old| old.package == sub.package) {
+            subscriptions.push(sub);
+        }
+    }
+    Ok(subscriptions)
+}
+
+/// The user's saved filters that still parse, paired with their names.
+pub async fn user_filters(db: &DatabaseImpl, user_id: &UserId) -> Result<Vec<(String, Filter)>> {
+    Ok(db
+        .get_filters(user_id)
+        .await?
+        .into_iter()
+        .filter_map(|saved| match saved.expression.parse() {
+            Ok(filter) => Some((saved.name, filter)),
+            Err(e) => {
+                warn!("Ignoring filter {} of {}: {}", saved.name, user_id, e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Updates of the packages the user is effectively subscribed to or their filters match, minus the snoozed ones and the pre-releases
+/// the user does not want to see.
+pub async fn user_updates(
+    db: &DatabaseImpl,
+    user_id: &UserId,
+    data: &Anicca,
+) -> Result<Vec<Package>> {
+    let subscriptions = effective_subscriptions(db, user_id).await?;
+    let mut updates = data.get_subscription_updates(&subscriptions)?;
+    let filters = user_filters(db, user_id)
+        .await?
+        .into_iter()
+        .map(|(_, filter)| filter)
+        .collect::<Vec<Filter>>();
+    for pkg in data.get_filter_updates(&filters)? {
+        if !updates.contains(&pkg) {
+            updates.push(pkg);
+        }
+    }
+
+    let now = duration::now();
+    let snoozes = db.get_snoozes(user_id).await?;
+    updates.retain(|pkg| !snoozes.iter().any(|snooze| snooze.suppresses(pkg, now)));
+
+    let hide_prerelease = db
+        .get_preference(user_id, Preference::HidePrerelease)
+        .await?
+        .is_some_and(|value| value == "true");
+    let overrides = db.get_prerelease_overrides(user_id).await?;
+    updates.retain(|pkg| {
+        let hide = overrides
+            .iter()
+            .find(|item| item.package == pkg.name)
+            .map_or(hide_prerelease, |item| !item.show);
+        !hide || !Version::parse(&pkg.after).is_prerelease()
+    });
+
+    Ok(updates)
+}
+
+pub fn delivered_updates(updates: &[Package]) -> Vec<Delivered> {
+    updates
+        .iter()
+        .map(|pkg| Delivered {
+            package: pkg.name.clone(),
+            after: pkg.after.clone(),
+        })
+        .collect()
+}
+
+pub async fn dm_or_create(client: Client, user_id: &UserId) -> Result<Room> {
     if let Some(room) = client.get_dm_room(user_id) {
         return Ok(room);
     }
     Ok(client.create_dm(user_id).await?)
 }
 
+/// Send `content` to the direct chat with the user, creating the chat if there is none yet.
+async fn deliver(
+    client: Client,
+    user_id: &UserId,
+    content: RoomMessageEventContent,
+) -> Result<OwnedEventId> {
+    let room = dm_or_create(client, user_id).await?;
+    Ok(room.send(content).await?.response.event_id)
+}
+
+/// Who a broadcast reached, and who it did not with the reason why.
+#[derive(Debug, Default)]
+pub struct DeliveryReport {
+    pub sent: usize,
+    pub failed: Vec<(OwnedUserId, String)>,
+}
+
+/// Send an announcement of the admins to every one of `recipients`, each with a header in their language.
+pub async fn broadcast(
+    client: Client,
+    db: &DatabaseImpl,
+    recipients: &[OwnedUserId],
+    message: &str,
+) -> DeliveryReport {
+    let mut report = DeliveryReport::default();
+    for user_id in recipients {
+        let lang = user_lang(db, user_id).await.unwrap_or_default();
+        let content = RoomMessageEventContent::notice_plain(format!(
+            "{}\n{message}",
+            t!(lang, "broadcast.header")
+        ));
+        match deliver(client.clone(), user_id, content).await {
+            Ok(_) => report.sent += 1,
+            Err(e) => {
+                warn!("Unable to broadcast to {}: {}", user_id, e);
+                report.failed.push((user_id.clone(), e.to_string()));
+            }
+        }
+    }
+    report
+}
+
+pub async fn wants_announcements(db: &DatabaseImpl, user_id: &UserId) -> Result<bool> {
+    Ok(db
+        .get_preference(user_id, Preference::Announcements)
+        .await?
+        .is_some_and(|value| value == "true"))
+}
+
+/// Send the changes of this version to the users who opted in, unless it was announced already.
+pub async fn announce_version(client: Client, db: DatabaseImpl) -> Result<()> {
+    let version = env!("CARGO_PKG_VERSION");
+    if db.get_announced_version().await?.as_deref() == Some(version) {
+        return Ok(());
+    }
+    // Recorded before sending, so an announcement failing halfway is not repeated on every restart.
+    db.set_announced_version(version).await?;
+    let changelog = Changelog::parse(CHANGELOG);
+    let Some(release) = changelog.get(version) else {
+        warn!(
+            "Not announcing version {}: It is not in the changelog.",
+            version
+        );
+        return Ok(());
+    };
+    let markdown = release.to_markdown();
+    let html = FormattedBody::markdown(&markdown)
+        .map(|body| body.body)
+        .unwrap_or_else(|| markdown.clone());
+    let mut announced = 0;
+    for user_id in db
+        .users_with_preference(Preference::Announcements, "true")
+        .await?
+    {
+        let lang = user_lang(&db, &user_id).await?;
+        let header = t!(lang, "announcements.header", version = version);
+        let footer = t!(lang, "announcements.footer");
+        let content = RoomMessageEventContent::notice_html(
+            format!("{header}\n{markdown}\n{footer}"),
+            format!("{header}<br/>{html}<br/>{footer}"),
+        );
+        match deliver(client.clone(), &user_id, content).await {
+            Ok(_) => announced += 1,
+            Err(e) => warn!(
+                "Unable to announce version {} to {}: {}",
+                version, user_id, e
+            ),
+        }
+    }
+    info!("Announced version {} to {} users.", version, announced);
+    Ok(())
+}
+
 async fn notify_user(
     client: Client,
     user_id: &UserId,
     db: DatabaseImpl,
     data_dir: &Path,
 ) -> Result<()> {
-    let room = dm_or_create(client.clone(), user_id).await?;
     info!("Notifying user: {}", user_id);
     let anicca_diff = Anicca::get_diff(data_dir).await?;
-    let packages = db.get_packages(user_id).await?;
-    let mut updates = anicca_diff.get_subscription_updates(&packages)?;
+    let mut updates = user_updates(&db, user_id, &anicca_diff).await?;
+    let delivered = db.get_delivered(user_id).await?;
+    updates.retain(|pkg| !delivered.iter().any(|item| item.covers(pkg)));
+    let tags = db.get_tags(user_id).await?;
+    let muted = muted_tags(&db, user_id).await?;
+    // Updates of muted tags are not marked as delivered either, so they are sent once the tag is unmuted.
+    updates.retain(|pkg| {
+        !tags
+            .iter()
+            .any(|tag| muted.contains(&tag.name) && tag.packages.contains(&pkg.name))
+    });
 
     if !updates.is_empty() {
-        let (plain_updates, html_updates) = format_update_packages(&mut updates);
-        let header = "(Hourly Notification)";
-        let plain_updates = format!("{header}\n{plain_updates}");
-        let html_updates = format!("{header}<br/>{html_updates}");
+        let lang = user_lang(&db, user_id).await?;
+        let history = History::load(data_dir).await?;
+        let (plain_updates, html_updates) =
+            format_updates(lang, &updates, &tags, &View::all(), Some(&history));
+        let header = t!(lang, "updates.notification");
+        let hint = t!(lang, "updates.reply_hint");
+        let plain_updates = format!("{header}\n{plain_updates}\n{hint}");
+        let html_updates = format!("{header}<br/>{html_updates}<br/>{hint}");
         let content = RoomMessageEventContent::notice_html(plain_updates, html_updates);
-        room.send(content).await?;
+        let event_id = deliver(client, user_id, content).await?;
+        let now = duration::now();
+        db.log_notification(
+            user_id,
+            LoggedNotification {
+                event_id: event_id.to_string(),
+                packages: updates.iter().map(|pkg| pkg.name.clone()).collect(),
+                sent: now,
+            },
+            now.saturating_sub(NOTIFICATION_LOG_TTL.as_secs()),
+        )
+        .await?;
+        db.mark_delivered(user_id, delivered_updates(&updates))
+            .await?;
     }
 
     Ok(())
 }
 
+/// Drop the snoozes that ended: their time is up, or the pending update in the full feed moved on to another version
+/// or the package left the feed.
+async fn prune_snoozes(db: &DatabaseImpl, user_id: &UserId, data: &Anicca, now: u64) -> Result<()> {
+    let stale = db
+        .get_snoozes(user_id)
+        .await?
+        .into_iter()
+        .filter(|snooze| {
+            !snooze.is_active(now)
+                || !data
+                    .packages()
+                    .iter()
+                    .any(|pkg| pkg.name == snooze.package && pkg.after == snooze.after)
+        })
+        .map(|snooze| snooze.package)
+        .collect::<Vec<String>>();
+    if !stale.is_empty() {
+        db.unsnooze(user_id, stale).await?;
+    }
+    Ok(())
+}
+
+/// Forget the updates sent of packages that left the feed, so they are sent again should the same update come back.
+async fn prune_delivered(db: &DatabaseImpl, user_id: &UserId, data: &Anicca) -> Result<()> {
+    let gone = db
+        .get_delivered(user_id)
+        .await?
+        .into_iter()
+        .filter(|item| !data.packages().iter().any(|pkg| pkg.name == item.package))
+        .map(|item| item.package)
+        .collect::<Vec<String>>();
+    if !gone.is_empty() {
+        db.clear_delivered(user_id, gone).await?;
+    }
+    Ok(())
+}
+
 pub async fn notify(client: Client, db: DatabaseImpl, data_dir: &Path) -> Result<()> {
+    let feed = Anicca::get_local_json(data_dir).await?;
+    let now = duration::now();
+    for user_id in db.users().await? {
+        prune_snoozes(&db, &user_id, &feed, now).await?;
+        prune_delivered(&db, &user_id, &feed).await?;
+    }
+
     let targets = db.notification_targets().await?;
     for user_id in targets.iter() {
         notify_user(client.clone(), user_id, db.clone(), data_dir).await?;
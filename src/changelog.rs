@@ -0,0 +1,190 @@
+//! Releases read from a changelog generated by git-cliff.
+//!
+//! Every release starts with a `## <version> - <date>` heading, or `## Unreleased`, and lists its commits under one
+//! `### <group>` heading per group.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub title: String,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    pub date: Option<String>,
+    pub sections: Vec<Section>,
+}
+
+impl Release {
+    /// The release as Markdown, heading included.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = match &self.date {
+            Some(date) => format!("## {} - {}\n", self.version, date),
+            None => format!("## {}\n", self.version),
+        };
+        for section in &self.sections {
+            markdown.push_str(&format!("### {}\n", section.title));
+            for item in &section.items {
+                markdown.push_str(&format!("- {item}\n"));
+            }
+            markdown.push('\n');
+        }
+        markdown.trim_end().to_owned()
+    }
+}
+
+/// Releases, newest first as git-cliff writes them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changelog(Vec<Release>);
+
+impl Changelog {
+    pub fn parse(text: &str) -> Self {
+        let mut releases: Vec<Release> = Vec::new();
+        for line in text.lines().map(str::trim_end) {
+            if let Some(heading) = line.strip_prefix("## ") {
+                let (version, date) = match heading.split_once(" - ") {
+                    Some((version, date)) => (version, Some(date.trim().to_owned())),
+                    None => (heading, None),
+                };
+                releases.push(Release {
+                    version: version.trim().trim_start_matches('v').to_owned(),
+                    date,
+                    sections: Vec::new(),
+                });
+                continue;
+            }
+            let Some(release) = releases.last_mut() else {
+                continue;
+            };
+            if let Some(title) = line.strip_prefix("### ") {
+                release.sections.push(Section {
+                    title: title.trim().to_owned(),
+                    items: Vec::new(),
+                });
+            } else if let Some(section) = release.sections.last_mut() {
+                if let Some(item) = line.strip_prefix("- ") {
+                    section.items.push(item.trim().to_owned());
+                } else if !line.trim().is_empty()
+                    && let Some(item) = section.items.last_mut()
+                {
+                    // Commit messages spanning several lines.
+                    item.push(' ');
+                    item.push_str(line.trim());
+                }
+            }
+        }
+        Self(releases)
+    }
+
+    pub fn releases(&self) -> &[Release] {
+        &self.0
+    }
+
+    /// The release of `version`, which may start with a `v`.
+    pub fn get(&self, version: &str) -> Option<&Release> {
+        let version = version.trim_start_matches('v');
+        self.0.iter().find(|release| release.version == version)
+    }
+
+    /// The newest `count` releases.
+    pub fn latest(&self, count: usize) -> &[Release] {
+        &self.0[..count.min(self.0.len())]
+    }
+
+    /// The releases newer than `version`, or `None` if there is no such version.
+    pub fn since(&self, version: &str) -> Option<&[Release]> {
+        let version = version.trim_start_matches('v');
+        let index = self
+            .0
+            .iter()
+            .position(|release| release.version == version)?;
+        Some(&self.0[..index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "## Unreleased
+### Fix
+- Fix a crash
+
+## 0.4.1 - 2025-11-19
+### Chore
+- Update deps
+- Release
+
+### Refactor
+- Set default value
+  for config path
+
+## 0.4.0 - 2025-07-19
+### Features
+- Add config file and admin commands
+";
+
+    #[test]
+    fn test_parse() {
+        let changelog = Changelog::parse(CHANGELOG);
+        assert_eq!(
+            changelog
+                .releases()
+                .iter()
+                .map(|release| release.version.as_str())
+                .collect::<Vec<&str>>(),
+            ["Unreleased", "0.4.1", "0.4.0"]
+        );
+        assert_eq!(
+            changelog.get("v0.4.1"),
+            Some(&Release {
+                version: "0.4.1".to_owned(),
+                date: Some("2025-11-19".to_owned()),
+                sections: vec![
+                    Section {
+                        title: "Chore".to_owned(),
+                        items: vec!["Update deps".to_owned(), "Release".to_owned()],
+                    },
+                    Section {
+                        title: "Refactor".to_owned(),
+                        items: vec!["Set default value for config path".to_owned()],
+                    },
+                ],
+            })
+        );
+        assert_eq!(changelog.get("0.3.0"), None);
+    }
+
+    #[test]
+    fn test_select() {
+        let changelog = Changelog::parse(CHANGELOG);
+        let versions = |releases: &[Release]| {
+            releases
+                .iter()
+                .map(|release| release.version.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(versions(changelog.latest(2)), ["Unreleased", "0.4.1"]);
+        assert_eq!(versions(changelog.latest(5)).len(), 3);
+        assert_eq!(
+            changelog.since("v0.4.0").map(versions),
+            Some(vec!["Unreleased".to_owned(), "0.4.1".to_owned()])
+        );
+        assert_eq!(changelog.since("Unreleased").map(versions), Some(vec![]));
+        assert_eq!(changelog.since("0.3.0"), None);
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let changelog = Changelog::parse(CHANGELOG);
+        assert_eq!(
+            changelog.get("0.4.0").unwrap().to_markdown(),
+            "## 0.4.0 - 2025-07-19\n### Features\n- Add config file and admin commands"
+        );
+        assert_eq!(
+            changelog.get("Unreleased").unwrap().to_markdown(),
+            "## Unreleased\n### Fix\n- Fix a crash"
+        );
+    }
+}
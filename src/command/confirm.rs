@@ -0,0 +1,117 @@
+//! Actions waiting for the user to confirm them with `!anic confirm <token>`, or by reacting to their preview.
+//!
+//! Pending actions only live in memory, so they are dropped on restart as well as once they expire.
+
+use anicca_subscribe::duration;
+use matrix_sdk::ruma::{EventId, OwnedEventId, OwnedUserId, UserId};
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use super::transfer::ImportPlan;
+
+pub const CONFIRM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug)]
+pub enum Action {
+    Import(ImportPlan),
+    /// Unsubscribe from these packages.
+    Unsubscribe(Vec<String>),
+    /// Delete everything stored about a user.
+    Purge(OwnedUserId),
+    /// Send an announcement to these users.
+    Broadcast {
+        recipients: Vec<OwnedUserId>,
+        message: String,
+    },
+}
+
+#[derive(Debug)]
+struct Pending {
+    user_id: OwnedUserId,
+    action: Action,
+    expires: u64,
+    /// The message showing the preview, once it is sent.
+    preview: Option<OwnedEventId>,
+}
+
+static PENDING: LazyLock<Mutex<HashMap<String, Pending>>> = LazyLock::new(Default::default);
+
+fn new_token(pending: &HashMap<String, Pending>) -> String {
+    loop {
+        let token = format!(
+            "{:06x}",
+            RandomState::new().hash_one(duration::now()) & 0xffffff
+        );
+        if !pending.contains_key(&token) {
+            return token;
+        }
+    }
+}
+
+/// Store `action` until `user_id` confirms it, returning the token to confirm it with.
+pub fn request(user_id: &UserId, action: Action) -> String {
+    let now = duration::now();
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|_, item| item.expires > now);
+    // A new request replaces the ones the user left unconfirmed.
+    pending.retain(|_, item| item.user_id != user_id);
+    let token = new_token(&pending);
+    pending.insert(
+        token.clone(),
+        Pending {
+            user_id: user_id.to_owned(),
+            action,
+            expires: now + CONFIRM_TIMEOUT.as_secs(),
+            preview: None,
+        },
+    );
+    token
+}
+
+/// Take the action `user_id` requested under `token`, unless it expired.
+pub fn take(user_id: &UserId, token: &str) -> Option<Action> {
+    let now = duration::now();
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|_, item| item.expires > now);
+    if pending.get(token)?.user_id != user_id {
+        return None;
+    }
+    pending.remove(token).map(|item| item.action)
+}
+
+/// Remember `event_id` as the preview of the action `user_id` is asked to confirm, if the message `body` names its
+/// token.
+pub fn attach_preview(user_id: &UserId, body: &str, event_id: &EventId) {
+    let mut pending = PENDING.lock().unwrap();
+    if let Some(item) = pending
+        .iter_mut()
+        .find(|(token, item)| item.user_id == user_id && body.contains(token.as_str()))
+        .map(|(_, item)| item)
+    {
+        item.preview = Some(event_id.to_owned());
+    }
+}
+
+/// Whether `user_id` has an action waiting whose preview is the message `event_id`.
+pub fn has_preview(user_id: &UserId, event_id: &EventId) -> bool {
+    let now = duration::now();
+    PENDING.lock().unwrap().values().any(|item| {
+        item.expires > now && item.user_id == user_id && item.preview.as_deref() == Some(event_id)
+    })
+}
+
+/// Take the action `user_id` requested whose preview is the message `event_id`, unless it expired.
+pub fn take_by_preview(user_id: &UserId, event_id: &EventId) -> Option<Action> {
+    let now = duration::now();
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|_, item| item.expires > now);
+    let token = pending
+        .iter()
+        .find(|(_, item)| item.user_id == user_id && item.preview.as_deref() == Some(event_id))
+        .map(|(token, _)| token.clone())?;
+    pending.remove(&token).map(|item| item.action)
+}
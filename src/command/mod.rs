@@ -0,0 +1,2474 @@
+use anicca_subscribe::{
+    anicca::{Anicca, Package, Subscription},
+    changelog::{Changelog, Release},
+    duration,
+    filter::Filter,
+    history::History,
+    search,
+    version::Constraint,
+};
+use eyre::Result;
+use matrix_sdk::{
+    Client, Room,
+    attachment::AttachmentConfig,
+    media::{MediaFormat, MediaRequestParameters},
+    ruma::{
+        EventId, RoomId, ServerName, UserId,
+        events::room::message::{FileMessageEventContent, FormattedBody, RoomMessageEventContent},
+    },
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    slice,
+    time::Duration,
+};
+use tracing::warn;
+
+use crate::{
+    bot::{
+        CHANGELOG, broadcast, delivered_updates, format_update_packages, format_updates,
+        is_list_public, muted_tags, set_muted_tags, user_filters, user_lang, user_subscriptions,
+        user_updates, wants_announcements,
+    },
+    config::Config,
+    db::*,
+    i18n::{Lang, t, tn},
+    invite,
+};
+
+pub mod confirm;
+mod parser;
+mod transfer;
+pub mod view;
+
+use confirm::{Action, CONFIRM_TIMEOUT};
+use parser::{ArgSpec, CommandSpec, FlagSpec, Invocation, ParseErrorKind};
+use transfer::{Export, ImportPlan};
+use view::{Item, View};
+
+pub const COMMAND_PREFIX: &str = "!anic";
+const DEFAULT_SNOOZE: Duration = Duration::from_secs(7 * 24 * 3600);
+const SEARCH_LIMIT: usize = 20;
+const SUGGESTION_LIMIT: usize = 3;
+const MAX_IMPORT_SIZE: usize = 1024 * 1024;
+const MAX_TAG_LENGTH: usize = 32;
+const TAG_FLAG: FlagSpec = FlagSpec::value("tag", "tag", "flag.tag");
+const SORT_FLAG: FlagSpec = FlagSpec::value("sort", "key", "flag.sort");
+const GROUP_FLAG: FlagSpec = FlagSpec::value("group", "mode", "flag.group");
+const PAGE_FLAG: FlagSpec = FlagSpec::value("page", "number", "flag.page");
+const PER_PAGE_FLAG: FlagSpec = FlagSpec::value("per-page", "number", "flag.per_page");
+const STATS_TOP: usize = 5;
+const DEFAULT_AUDIT_COUNT: usize = 20;
+const CHANGELOG_RELEASES: usize = 3;
+
+// Summaries are message catalog keys.
+static COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("help", "command.help").args(&[ArgSpec::optional_list("command")]),
+    CommandSpec::new("lang", "command.lang").args(&[ArgSpec::optional("language")]),
+    CommandSpec::new("search", "command.search").args(&[ArgSpec::required("query")]),
+    CommandSpec::new("info", "command.info").args(&[ArgSpec::required("package")]),
+    CommandSpec::new("list", "command.list").flags(&[
+        TAG_FLAG,
+        SORT_FLAG,
+        GROUP_FLAG,
+        PAGE_FLAG,
+        PER_PAGE_FLAG,
+    ]),
+    CommandSpec::new("subscribe", "command.subscribe")
+        .args(&[ArgSpec::list("packages")])
+        .flags(&[FlagSpec::switch("force", "flag.subscribe_force")]),
+    CommandSpec::new("unsubscribe", "command.unsubscribe")
+        .args(&[ArgSpec::optional_list("packages")])
+        .flags(&[
+            TAG_FLAG,
+            FlagSpec::switch("all", "flag.unsubscribe_all"),
+            FlagSpec::value("pattern", "glob", "flag.pattern"),
+            FlagSpec::value("section", "section", "flag.section"),
+        ]),
+    CommandSpec::new("updates", "command.updates").flags(&[
+        TAG_FLAG,
+        SORT_FLAG,
+        GROUP_FLAG,
+        PAGE_FLAG,
+        PER_PAGE_FLAG,
+    ]),
+    CommandSpec::new("snooze", "command.snooze")
+        .args(&[ArgSpec::required("package"), ArgSpec::optional("duration")]),
+    CommandSpec::new("ack", "command.ack").args(&[ArgSpec::list("packages")]),
+    CommandSpec::new("unsnooze", "command.unsnooze")
+        .args(&[ArgSpec::optional_list("packages")])
+        .flags(&[FlagSpec::switch("all", "flag.unsnooze_all")]),
+    CommandSpec::new("prerelease", "command.prerelease").subcommands(&[
+        CommandSpec::new("show", "command.prerelease_show")
+            .args(&[ArgSpec::optional_list("packages")]),
+        CommandSpec::new("hide", "command.prerelease_hide")
+            .args(&[ArgSpec::optional_list("packages")]),
+        CommandSpec::new("default", "command.prerelease_default")
+            .args(&[ArgSpec::list("packages")]),
+    ]),
+    CommandSpec::new("filter", "").subcommands(&[
+        CommandSpec::new("add", "command.filter_add")
+            .args(&[ArgSpec::required("name"), ArgSpec::raw("expression")]),
+        CommandSpec::new("list", "command.filter_list"),
+        CommandSpec::new("remove", "command.filter_remove").args(&[ArgSpec::list("names")]),
+        CommandSpec::new("test", "command.filter_test").args(&[ArgSpec::raw("filter")]),
+    ]),
+    CommandSpec::new("collection", "").subcommands(&[
+        CommandSpec::new("list", "command.collection_list"),
+        CommandSpec::new("subscribe", "command.collection_subscribe")
+            .args(&[ArgSpec::list("names")]),
+        CommandSpec::new("unsubscribe", "command.collection_unsubscribe")
+            .args(&[ArgSpec::list("names")]),
+    ]),
+    CommandSpec::new("tag", "").subcommands(&[
+        CommandSpec::new("list", "command.tag_list"),
+        CommandSpec::new("add", "command.tag_add")
+            .args(&[ArgSpec::required("tag"), ArgSpec::list("packages")]),
+        CommandSpec::new("remove", "command.tag_remove")
+            .args(&[ArgSpec::required("tag"), ArgSpec::optional_list("packages")]),
+    ]),
+    CommandSpec::new("public", "command.public").subcommands(&[
+        CommandSpec::new("on", "command.public_on"),
+        CommandSpec::new("off", "command.public_off"),
+    ]),
+    CommandSpec::new("announcements", "command.announcements").subcommands(&[
+        CommandSpec::new("on", "command.announcements_on"),
+        CommandSpec::new("off", "command.announcements_off"),
+    ]),
+    CommandSpec::new("follow", "command.follow").args(&[ArgSpec::required("user")]),
+    CommandSpec::new("unfollow", "command.unfollow").args(&[ArgSpec::list("users")]),
+    CommandSpec::new("export", "command.export").args(&[ArgSpec::optional("format")]),
+    CommandSpec::new("confirm", "command.confirm").args(&[ArgSpec::required("token")]),
+    CommandSpec::new("enable-notification", "command.enable_notification").flags(&[TAG_FLAG]),
+    CommandSpec::new("disable-notification", "command.disable_notification").flags(&[TAG_FLAG]),
+    CommandSpec::new("version", "command.version"),
+    CommandSpec::new("changelog", "command.changelog")
+        .args(&[ArgSpec::optional("version")])
+        .subcommands(&[
+            CommandSpec::new("latest", "command.changelog_latest"),
+            CommandSpec::new("since", "command.changelog_since")
+                .args(&[ArgSpec::required("version")]),
+        ]),
+    CommandSpec::new("ping", "command.ping"),
+    CommandSpec::new("+users", "command.admin_users").admin(),
+    CommandSpec::new("+list", "command.admin_list")
+        .args(&[ArgSpec::required("user")])
+        .flags(&[SORT_FLAG, GROUP_FLAG, PAGE_FLAG, PER_PAGE_FLAG])
+        .admin(),
+    CommandSpec::new("+updates", "command.admin_updates")
+        .args(&[ArgSpec::required("user")])
+        .flags(&[SORT_FLAG, GROUP_FLAG, PAGE_FLAG, PER_PAGE_FLAG])
+        .admin(),
+    CommandSpec::new("+subscribe", "command.admin_subscribe")
+        .args(&[ArgSpec::required("user"), ArgSpec::list("packages")])
+        .flags(&[FlagSpec::switch("force", "flag.subscribe_force")])
+        .admin(),
+    CommandSpec::new("+unsubscribe", "command.admin_unsubscribe")
+        .args(&[ArgSpec::required("user"), ArgSpec::list("packages")])
+        .admin(),
+    CommandSpec::new("+notify-on", "command.admin_notify_on")
+        .args(&[ArgSpec::required("user")])
+        .admin(),
+    CommandSpec::new("+notify-off", "command.admin_notify_off")
+        .args(&[ArgSpec::required("user")])
+        .admin(),
+    CommandSpec::new("+purge", "command.admin_purge")
+        .args(&[ArgSpec::required("user")])
+        .admin(),
+    CommandSpec::new("+broadcast", "command.admin_broadcast")
+        .args(&[ArgSpec::raw("message")])
+        .flags(&[
+            FlagSpec::switch("targets", "flag.broadcast_targets"),
+            FlagSpec::value("collection", "name", "flag.broadcast_collection"),
+        ])
+        .admin(),
+    CommandSpec::new("+stats", "command.admin_stats").admin(),
+    CommandSpec::new("+audit", "command.admin_audit")
+        .args(&[ArgSpec::optional("count")])
+        .admin(),
+    CommandSpec::new("+deny", "command.admin_deny")
+        .args(&[ArgSpec::list("entries")])
+        .admin(),
+    CommandSpec::new("+undeny", "command.admin_undeny")
+        .args(&[ArgSpec::list("entries")])
+        .admin(),
+    CommandSpec::new("+denylist", "command.admin_denylist").admin(),
+    CommandSpec::new("+invite", "")
+        .subcommands(&[
+            CommandSpec::new("list", "command.admin_invite_list"),
+            CommandSpec::new("approve", "command.admin_invite_approve")
+                .args(&[ArgSpec::required("room")]),
+            CommandSpec::new("reject", "command.admin_invite_reject")
+                .args(&[ArgSpec::required("room")]),
+        ])
+        .admin(),
+    CommandSpec::new("+collection", "")
+        .subcommands(&[
+            CommandSpec::new("add", "command.admin_collection_add")
+                .args(&[ArgSpec::required("name"), ArgSpec::list("packages")]),
+            CommandSpec::new("remove", "command.admin_collection_remove")
+                .args(&[ArgSpec::required("name"), ArgSpec::list("packages")]),
+            CommandSpec::new("delete", "command.admin_collection_delete")
+                .args(&[ArgSpec::required("name")]),
+        ])
+        .admin(),
+];
+
+/// Commands that, sent as a reply to a notification, act on the packages listed in it.
+static REPLY_COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("unsubscribe", "command.unsubscribe"),
+    CommandSpec::new("snooze", "command.snooze").args(&[ArgSpec::optional("duration")]),
+    CommandSpec::new("ack", "command.ack"),
+];
+
+/// Strip the command prefix, or the bot mention, off a message. Direct messages need neither.
+pub fn strip_command_prefix<'a>(
+    is_direct: bool,
+    text: &'a str,
+    bot_user_id: Option<&UserId>,
+    display_name: &str,
+) -> Option<&'a str> {
+    let trimmed = text.trim_start();
+    let (first, rest) = trimmed
+        .split_once(char::is_whitespace)
+        .unwrap_or((trimmed, ""));
+    if first == COMMAND_PREFIX
+        || Some(first) == bot_user_id.map(|x| x.as_str())
+        || Some(first) == bot_user_id.map(|x| format!("{}:", x)).as_deref()
+    {
+        Some(rest)
+    } else if let Some(rest) = text.strip_prefix(&(display_name.to_owned() + ": ")) {
+        Some(rest)
+    } else if is_direct {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+/// Drop the quote of the replied-to message that some clients still put in front of a reply.
+pub fn strip_reply_fallback(text: &str) -> &str {
+    let mut rest = text;
+    while rest.starts_with('>') {
+        rest = rest.split_once('\n').map_or("", |(_, line)| line);
+    }
+    rest.trim_start()
+}
+
+fn is_admin_command(name: &str) -> bool {
+    COMMANDS.iter().any(|spec| spec.name == name && spec.admin)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn html_to_plain(html: &str) -> String {
+    html.replace("<code>", "`")
+        .replace("</code>", "`")
+        .replace("<br/>", "\n")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+pub fn html_reply(html: String) -> RoomMessageEventContent {
+    RoomMessageEventContent::notice_html(html_to_plain(&html), html)
+}
+
+/// Turn the backticks of a summary into `<code>` tags.
+fn summary_html(summary: &str) -> String {
+    escape_html(summary)
+        .split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<code>{part}</code>")
+            } else {
+                part.to_owned()
+            }
+        })
+        .collect()
+}
+
+fn usage_lines_html(lang: Lang, lines: &[(String, &str)]) -> String {
+    lines
+        .iter()
+        .map(|(usage, summary)| {
+            format!(
+                "<code>{}</code> - {}<br/>",
+                escape_html(usage),
+                summary_html(&t!(lang, summary))
+            )
+        })
+        .collect()
+}
+
+fn usage_reply(lang: Lang, message: &str, usage: &str) -> RoomMessageEventContent {
+    html_reply(format!(
+        "{}<br/>{} <code>{}</code>",
+        escape_html(message),
+        t!(lang, "help.usage"),
+        escape_html(usage)
+    ))
+}
+
+fn help_overview(lang: Lang, show_admin: bool) -> RoomMessageEventContent {
+    let lines = COMMANDS
+        .iter()
+        .filter(|spec| show_admin || !spec.admin)
+        .flat_map(|spec| spec.usages(&mut Vec::new()))
+        .collect::<Vec<_>>();
+    html_reply(format!(
+        "{}<br/>{}{}",
+        t!(lang, "help.intro"),
+        usage_lines_html(lang, &lines),
+        t!(lang, "help.footer")
+    ))
+}
+
+fn help_command(lang: Lang, path: &[&'static str], spec: &CommandSpec) -> RoomMessageEventContent {
+    let mut prefix = path[..path.len() - 1].to_vec();
+    let mut html = usage_lines_html(lang, &spec.usages(&mut prefix));
+    if !spec.flags.is_empty() {
+        html.push_str(&format!("{}<br/>", t!(lang, "help.flags")));
+        for flag in spec.flags {
+            html.push_str(&format!(
+                "<code>{}</code> - {}<br/>",
+                escape_html(&flag.to_string()),
+                summary_html(&t!(lang, flag.summary))
+            ));
+        }
+    }
+    html_reply(html.trim_end_matches("<br/>").to_owned())
+}
+
+fn parse_error_message(lang: Lang, kind: ParseErrorKind) -> String {
+    match kind {
+        ParseErrorKind::Empty => t!(lang, "reply.no_command"),
+        ParseErrorKind::UnknownCommand(name) => t!(lang, "reply.unknown_command", name = name),
+        ParseErrorKind::UnterminatedQuote => t!(lang, "error.unterminated_quote"),
+        ParseErrorKind::MissingArgument(name) => t!(lang, "error.missing_argument", name = name),
+        ParseErrorKind::UnexpectedArgument(arg) => {
+            t!(lang, "error.unexpected_argument", arg = arg)
+        }
+        ParseErrorKind::UnknownFlag(flag) => t!(lang, "error.unknown_flag", flag = flag),
+        ParseErrorKind::MissingFlagValue(flag) => t!(lang, "error.missing_flag_value", flag = flag),
+        ParseErrorKind::UnexpectedFlagValue(flag) => {
+            t!(lang, "error.unexpected_flag_value", flag = flag)
+        }
+    }
+}
+
+pub async fn handle(
+    config: Config,
+    data_dir: &Path,
+    db: DatabaseImpl,
+    user_id: &UserId,
+    room: Room,
+    text: &str,
+    reply_to: Option<&EventId>,
+) -> Result<RoomMessageEventContent> {
+    let lang = user_lang(&db, user_id).await?;
+    // Admin commands are accepted in direct messages and the admin room.
+    let is_admin = config.is_admin(user_id)
+        && (config.admin_room.as_deref() == Some(room.room_id()) || room.is_direct().await?);
+    let unknown_command =
+        |name: &str| html_reply(t!(lang, "reply.unknown_command", name = escape_html(name)));
+
+    if let Some(event_id) = reply_to
+        && let Ok(inv) = parser::parse(REPLY_COMMANDS, text)
+        && !inv.flag("help")
+    {
+        return handle_reply(&db, data_dir, lang, user_id, event_id, &inv).await;
+    }
+    let inv = match parser::parse(COMMANDS, text) {
+        Ok(inv) if inv.admin && !is_admin => return Ok(unknown_command(inv.path[0])),
+        Ok(inv) => inv,
+        Err(e) => {
+            return Ok(match (e.kind, e.usage) {
+                (ParseErrorKind::UnknownCommand(name), _) => unknown_command(&name),
+                (kind @ ParseErrorKind::Empty, _) => html_reply(parse_error_message(lang, kind)),
+                (kind, Some(usage)) => usage_reply(lang, &parse_error_message(lang, kind), &usage),
+                (kind, None) => {
+                    RoomMessageEventContent::notice_plain(parse_error_message(lang, kind))
+                }
+            });
+        }
+    };
+    if inv.flag("help") {
+        return Ok(help_command(lang, &inv.path, inv.spec));
+    }
+    // Every command taking `--tag` needs a tag the user has.
+    let tag = match inv.flag_value("tag") {
+        None => None,
+        Some(name) => match db
+            .get_tags(user_id)
+            .await?
+            .into_iter()
+            .find(|tag| tag.name == name)
+        {
+            Some(tag) => Some(tag),
+            None => {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "tag.unknown",
+                    tag = name
+                )));
+            }
+        },
+    };
+    let view = match View::parse(
+        inv.flag_value("sort"),
+        inv.flag_value("group"),
+        inv.flag_value("page"),
+        inv.flag_value("per-page"),
+    ) {
+        Ok(view) => view,
+        Err(e) => {
+            return Ok(usage_reply(
+                lang,
+                &e.message(lang),
+                &inv.spec.usage(&inv.path),
+            ));
+        }
+    };
+
+    let reply = match inv.path.as_slice() {
+        ["help"] => {
+            let topic = inv.list("command");
+            if topic.is_empty() {
+                return Ok(help_overview(lang, is_admin));
+            }
+            match CommandSpec::resolve(COMMANDS, topic.iter().map(|x| x.as_str())) {
+                Some((path, spec)) if is_admin || !is_admin_command(path[0]) => {
+                    Ok(help_command(lang, &path, spec))
+                }
+                _ => Ok(unknown_command(&topic.join(" "))),
+            }
+        }
+        ["lang"] => {
+            let languages = Lang::ALL
+                .iter()
+                .map(|lang| format!("{} ({})", lang.name(), lang.code()))
+                .collect::<Vec<String>>()
+                .join(", ");
+            match inv.arg("language") {
+                None => Ok(RoomMessageEventContent::notice_plain(format!(
+                    "{}\n{}",
+                    t!(lang, "lang.current", name = lang.name(), code = lang.code()),
+                    t!(lang, "lang.available", languages = languages)
+                ))),
+                Some(code) => match code.parse::<Lang>() {
+                    Ok(new_lang) => {
+                        db.set_preference(
+                            user_id,
+                            Preference::Language,
+                            Some(new_lang.code().to_owned()),
+                        )
+                        .await?;
+                        Ok(RoomMessageEventContent::notice_plain(t!(
+                            new_lang,
+                            "lang.set",
+                            name = new_lang.name()
+                        )))
+                    }
+                    Err(_) => Ok(RoomMessageEventContent::notice_plain(format!(
+                        "{}\n{}",
+                        t!(lang, "lang.unknown", code = code),
+                        t!(lang, "lang.available", languages = languages)
+                    ))),
+                },
+            }
+        }
+        ["version"] => {
+            let version = env!("CARGO_PKG_VERSION");
+            #[cfg(feature = "sqlite")]
+            let backend = "SQLite";
+            #[cfg(feature = "rocksdb")]
+            let backend = "RocksDB";
+            Ok(RoomMessageEventContent::notice_html(
+                version.to_owned(),
+                t!(
+                    lang,
+                    "reply.version",
+                    version = format!(
+                        "<a href=\"https://factoria.origincode.me/OriginCode/anicca-subscribe-matrix/-/tree/v{version}?ref_type=tags\">{version}</a>"
+                    ),
+                    backend = backend
+                ),
+            ))
+        }
+        ["changelog"] => {
+            let changelog = Changelog::parse(CHANGELOG);
+            match inv.arg("version") {
+                None => {
+                    let releases = changelog.latest(CHANGELOG_RELEASES);
+                    let header = t!(
+                        lang,
+                        "changelog.latest",
+                        releases = tn!(lang, "count.release", releases.len())
+                    );
+                    let footer = t!(lang, "changelog.older");
+                    Ok(changelog_reply(&header, releases, &footer))
+                }
+                Some(version) => match changelog.get(version) {
+                    Some(release) => Ok(changelog_reply("", slice::from_ref(release), "")),
+                    None => Ok(unknown_version(lang, &changelog, version)),
+                },
+            }
+        }
+        ["changelog", "latest"] => {
+            let changelog = Changelog::parse(CHANGELOG);
+            Ok(changelog_reply("", changelog.latest(1), ""))
+        }
+        ["changelog", "since"] => {
+            let changelog = Changelog::parse(CHANGELOG);
+            let version = inv.arg("version").unwrap();
+            match changelog.since(version) {
+                None => Ok(unknown_version(lang, &changelog, version)),
+                Some([]) => Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "changelog.none_since",
+                    version = version
+                ))),
+                Some(releases) => {
+                    let header = t!(
+                        lang,
+                        "changelog.since",
+                        releases = tn!(lang, "count.release", releases.len()),
+                        version = version
+                    );
+                    Ok(changelog_reply(&header, releases, ""))
+                }
+            }
+        }
+        ["ping"] => Ok(RoomMessageEventContent::notice_plain(t!(
+            lang,
+            "reply.pong"
+        ))),
+        ["collection", "list"] => {
+            let collections = db.get_collections().await?;
+            if collections.is_empty() {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "collection.none"
+                )));
+            }
+            let following = db.get_collection_subscriptions(user_id).await?;
+            let mut html = format!(
+                "<strong>{}</strong><ul>",
+                t!(
+                    lang,
+                    "collection.list",
+                    collections = tn!(lang, "count.collection", collections.len())
+                )
+            );
+            for collection in &collections {
+                html.push_str(&format!(
+                    "<li><code>{}</code>{} ({}): {}</li>",
+                    escape_html(&collection.name),
+                    if following.contains(&collection.name) {
+                        " [✓]"
+                    } else {
+                        ""
+                    },
+                    tn!(lang, "count.package", collection.packages.len()),
+                    escape_html(&collection.packages.join(", "))
+                ));
+            }
+            html.push_str("</ul>");
+            let plain = html
+                .replace("<strong>", "")
+                .replace("</strong>", "\n")
+                .replace("<ul>", "")
+                .replace("</ul>", "")
+                .replace("<li>", "- ")
+                .replace("</li>", "\n");
+            Ok(RoomMessageEventContent::notice_html(
+                html_to_plain(plain.trim_end()),
+                html,
+            ))
+        }
+        ["collection", "subscribe"] => {
+            let collections = db.get_collections().await?;
+            let known = collections
+                .iter()
+                .map(|collection| collection.name.as_str())
+                .collect::<Vec<&str>>();
+            let (names, unknown): (Vec<String>, Vec<String>) = inv
+                .list("names")
+                .iter()
+                .cloned()
+                .partition(|name| known.contains(&name.as_str()));
+            let mut lines = Vec::new();
+            if !names.is_empty() {
+                lines.push(t!(lang, "collection.subscribed", names = names.join(", ")));
+                db.subscribe_collections(user_id, names).await?;
+            }
+            for name in unknown {
+                let suggestions = search::suggest(&name, known.iter().copied(), SUGGESTION_LIMIT);
+                lines.push(if suggestions.is_empty() {
+                    t!(lang, "collection.unknown", name = name)
+                } else {
+                    t!(
+                        lang,
+                        "collection.did_you_mean",
+                        name = name,
+                        suggestions = suggestions.join(", ")
+                    )
+                });
+            }
+            Ok(RoomMessageEventContent::notice_plain(lines.join("\n")))
+        }
+        ["collection", "unsubscribe"] => {
+            db.unsubscribe_collections(user_id, inv.list("names").to_vec())
+                .await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "unsubscribe.done"
+            )))
+        }
+        ["tag", "list"] => {
+            let tags = db.get_tags(user_id).await?;
+            if tags.is_empty() {
+                return Ok(RoomMessageEventContent::notice_plain(t!(lang, "tag.none")));
+            }
+            let muted = muted_tags(&db, user_id).await?;
+            let mut lines = vec![t!(
+                lang,
+                "tag.list",
+                tags = tn!(lang, "count.tag", tags.len())
+            )];
+            for tag in &tags {
+                lines.push(format!(
+                    "- {}{} ({}): {}",
+                    tag.name,
+                    if muted.contains(&tag.name) {
+                        t!(lang, "tag.muted_mark")
+                    } else {
+                        String::new()
+                    },
+                    tn!(lang, "count.package", tag.packages.len()),
+                    tag.packages.join(", ")
+                ));
+            }
+            Ok(RoomMessageEventContent::notice_plain(lines.join("\n")))
+        }
+        ["tag", "add"] => {
+            let name = inv.arg("tag").unwrap();
+            if !is_valid_tag(name) {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "tag.invalid",
+                    tag = name,
+                    max = MAX_TAG_LENGTH
+                )));
+            }
+            db.add_to_tag(user_id, name, inv.list("packages").to_vec())
+                .await?;
+            tag_updated(&db, lang, user_id, name).await
+        }
+        ["tag", "remove"] => {
+            let name = inv.arg("tag").unwrap();
+            if !db
+                .get_tags(user_id)
+                .await?
+                .iter()
+                .any(|tag| tag.name == name)
+            {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "tag.unknown",
+                    tag = name
+                )));
+            }
+            let packages = inv.list("packages");
+            if packages.is_empty() {
+                db.delete_tag(user_id, name).await?;
+            } else {
+                db.remove_from_tag(user_id, name, packages.to_vec()).await?;
+            }
+            if db
+                .get_tags(user_id)
+                .await?
+                .iter()
+                .any(|tag| tag.name == name)
+            {
+                return tag_updated(&db, lang, user_id, name).await;
+            }
+            // A tag is gone along with its last package, so it should not stay muted either.
+            let mut muted = muted_tags(&db, user_id).await?;
+            muted.retain(|muted| muted != name);
+            set_muted_tags(&db, user_id, muted).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "tag.deleted",
+                tag = name
+            )))
+        }
+        ["public"] => Ok(RoomMessageEventContent::notice_plain(
+            if is_list_public(&db, user_id).await? {
+                t!(lang, "public.on")
+            } else {
+                t!(lang, "public.off")
+            },
+        )),
+        ["public", action @ ("on" | "off")] => {
+            let public = *action == "on";
+            db.set_preference(
+                user_id,
+                Preference::PublicList,
+                public.then(|| "true".to_owned()),
+            )
+            .await?;
+            Ok(RoomMessageEventContent::notice_plain(if public {
+                t!(lang, "public.now_on", user = user_id)
+            } else {
+                t!(lang, "public.now_off")
+            }))
+        }
+        ["announcements"] => Ok(RoomMessageEventContent::notice_plain(
+            if wants_announcements(&db, user_id).await? {
+                t!(lang, "announcements.on")
+            } else {
+                t!(lang, "announcements.off")
+            },
+        )),
+        ["announcements", action @ ("on" | "off")] => {
+            let on = *action == "on";
+            db.set_preference(
+                user_id,
+                Preference::Announcements,
+                on.then(|| "true".to_owned()),
+            )
+            .await?;
+            Ok(RoomMessageEventContent::notice_plain(if on {
+                t!(lang, "announcements.now_on")
+            } else {
+                t!(lang, "announcements.now_off")
+            }))
+        }
+        ["follow"] => {
+            let arg = inv.arg("user").unwrap();
+            let Ok(followed) = UserId::parse(arg) else {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "follow.invalid",
+                    user = arg
+                )));
+            };
+            if followed == user_id {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "follow.self"
+                )));
+            }
+            // Private and unknown users look the same, so nobody can probe who uses the bot.
+            if !is_list_public(&db, &followed).await? {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "follow.private",
+                    user = followed
+                )));
+            }
+            db.follow(user_id, &followed).await?;
+            let packages = db.get_packages(&followed).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "follow.done",
+                user = followed,
+                packages = tn!(lang, "count.package", packages.len())
+            )))
+        }
+        ["unfollow"] => {
+            let mut followed = Vec::new();
+            for arg in inv.list("users") {
+                match UserId::parse(arg) {
+                    Ok(id) => followed.push(id),
+                    Err(_) => {
+                        return Ok(RoomMessageEventContent::notice_plain(t!(
+                            lang,
+                            "follow.invalid",
+                            user = arg
+                        )));
+                    }
+                }
+            }
+            db.unfollow(user_id, followed).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "follow.unfollowed"
+            )))
+        }
+        ["export"] => {
+            let export = Export::collect(&db, user_id).await?;
+            let (filename, content_type, data) = match inv.arg("format").unwrap_or("json") {
+                "json" => (
+                    "anicca-subscriptions.json",
+                    mime::APPLICATION_JSON,
+                    export.to_json()?,
+                ),
+                "text" => (
+                    "anicca-subscriptions.txt",
+                    mime::TEXT_PLAIN_UTF_8,
+                    export.to_text(),
+                ),
+                format => {
+                    return Ok(RoomMessageEventContent::notice_plain(t!(
+                        lang,
+                        "export.invalid_format",
+                        format = format
+                    )));
+                }
+            };
+            room.send_attachment(
+                filename,
+                &content_type,
+                data.into_bytes(),
+                AttachmentConfig::new(),
+            )
+            .await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "export.done",
+                packages = tn!(lang, "count.package", export.subscriptions.len()),
+                filters = tn!(lang, "count.filter", export.filters.len())
+            )))
+        }
+        ["confirm"] => match confirm::take(user_id, inv.arg("token").unwrap()) {
+            None => Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "confirm.unknown"
+            ))),
+            Some(action) => apply_confirmed(&room.client(), &db, lang, user_id, action).await,
+        },
+        ["search"] => {
+            let query = inv.arg("query").unwrap();
+            if query.chars().count() > search::MAX_QUERY_LEN {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "search.too_long",
+                    max = search::MAX_QUERY_LEN
+                )));
+            }
+            let data = Anicca::get_local_json(data_dir).await?;
+            Ok(format_search_results(lang, query, &data.search(query)))
+        }
+        ["info"] => {
+            let name = inv.arg("package").unwrap();
+            let data = Anicca::get_local_json(data_dir).await?;
+            let history = History::load(data_dir).await?;
+            let package = data.packages().iter().find(|pkg| pkg.name == name);
+            let (path, status) = match (package, history.get(name)) {
+                (Some(pkg), _) => (
+                    &pkg.path,
+                    PackageStatus::Pending {
+                        package: pkg,
+                        pending_for: history.pending_for(name, &pkg.after),
+                    },
+                ),
+                (None, Some(entry)) => (&entry.path, PackageStatus::UpToDate(&entry.after)),
+                (None, None) => {
+                    return Ok(html_reply(t!(
+                        lang,
+                        "info.unknown",
+                        package = escape_html(name)
+                    )));
+                }
+            };
+            let subscribers = db.subscribers(name).await?;
+            Ok(format_package_info(
+                lang,
+                name,
+                path,
+                status,
+                subscribers.len(),
+                subscribers.iter().any(|id| id == user_id),
+            ))
+        }
+        ["list"] => list_reply(&db, data_dir, lang, user_id, &view, tag.as_ref()).await,
+        ["subscribe"] => {
+            let data = Anicca::get_local_json(data_dir).await?;
+            let (new, html) = match subscribe_specs(
+                &db,
+                data_dir,
+                &data,
+                lang,
+                user_id,
+                inv.list("packages"),
+                inv.flag("force"),
+            )
+            .await?
+            {
+                Ok(subscribed) => subscribed,
+                Err(reply) => return Ok(reply),
+            };
+
+            // Updates pending before the subscription never show up in a later diff.
+            let mut updates = user_updates(&db, user_id, &data).await?;
+            updates.retain(|pkg| new.iter().any(|sub| sub.package == pkg.name));
+            if updates.is_empty() {
+                return Ok(html_reply(html));
+            }
+            db.mark_delivered(user_id, delivered_updates(&updates))
+                .await?;
+            let (plain_updates, html_updates) = format_update_packages(lang, &updates);
+            Ok(RoomMessageEventContent::notice_html(
+                format!("{}\n{plain_updates}", html_to_plain(&html)),
+                format!("{html}<br/>{html_updates}"),
+            ))
+        }
+        ["unsubscribe"] => {
+            let mut packages = inv.list("packages").to_vec();
+            if let Some(tag) = tag {
+                packages.extend(tag.packages);
+            }
+            let pattern = inv.flag_value("pattern");
+            let section = inv.flag_value("section");
+            // Bulk unsubscribing is only done once the user confirms the preview.
+            if inv.flag("all") || pattern.is_some() || section.is_some() {
+                for package in
+                    select_subscriptions(&db, data_dir, user_id, pattern, section).await?
+                {
+                    if !packages.contains(&package) {
+                        packages.push(package);
+                    }
+                }
+                if packages.is_empty() {
+                    return Ok(RoomMessageEventContent::notice_plain(t!(
+                        lang,
+                        "unsubscribe.nothing"
+                    )));
+                }
+                let preview = t!(
+                    lang,
+                    "unsubscribe.preview",
+                    packages = tn!(lang, "count.package", packages.len()),
+                    list = code_list(&packages)
+                );
+                let token = confirm::request(user_id, Action::Unsubscribe(packages));
+                return Ok(html_reply(format!(
+                    "{preview}<br/>{}",
+                    t!(
+                        lang,
+                        "unsubscribe.confirm",
+                        token = token,
+                        duration = duration::format(CONFIRM_TIMEOUT)
+                    )
+                )));
+            }
+            if packages.is_empty() {
+                return Ok(usage_reply(
+                    lang,
+                    &t!(lang, "unsubscribe.missing"),
+                    &inv.spec.usage(&inv.path),
+                ));
+            }
+            unsubscribe_packages(&db, user_id, packages).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "unsubscribe.done"
+            )))
+        }
+        ["updates"] => updates_reply(&db, data_dir, lang, user_id, &view, tag).await,
+        ["snooze"] => {
+            let package = inv.arg("package").unwrap().to_owned();
+            let snooze_for = match snooze_duration(lang, inv.arg("duration")) {
+                Ok(snooze_for) => snooze_for,
+                Err(reply) => return Ok(reply),
+            };
+            let (mut updates, _) = snooze_updates(
+                &db,
+                data_dir,
+                user_id,
+                slice::from_ref(&package),
+                Some(duration::now() + snooze_for.as_secs()),
+            )
+            .await?;
+            let Some(update) = updates.pop() else {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "reply.not_pending",
+                    packages = package
+                )));
+            };
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "snooze.done",
+                package = package,
+                duration = duration::format(snooze_for),
+                version = update.after
+            )))
+        }
+        ["ack"] => {
+            let (updates, not_pending) =
+                snooze_updates(&db, data_dir, user_id, inv.list("packages"), None).await?;
+            Ok(snoozed_reply(lang, &updates, &not_pending, None))
+        }
+        ["unsnooze"] => {
+            let packages = if inv.flag("all") {
+                db.get_snoozes(user_id)
+                    .await?
+                    .into_iter()
+                    .map(|snooze| snooze.package)
+                    .collect()
+            } else if inv.list("packages").is_empty() {
+                return Ok(usage_reply(
+                    lang,
+                    &t!(lang, "unsnooze.missing"),
+                    &inv.spec.usage(&inv.path),
+                ));
+            } else {
+                inv.list("packages").to_vec()
+            };
+            db.unsnooze(user_id, packages).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "unsnooze.done"
+            )))
+        }
+        ["prerelease"] => {
+            let hidden = db
+                .get_preference(user_id, Preference::HidePrerelease)
+                .await?
+                .is_some_and(|value| value == "true");
+            let overrides = db.get_prerelease_overrides(user_id).await?;
+            let mut reply = vec![if hidden {
+                t!(lang, "prerelease.hidden")
+            } else {
+                t!(lang, "prerelease.shown")
+            }];
+            if !overrides.is_empty() {
+                reply.push(t!(
+                    lang,
+                    "prerelease.overrides",
+                    overrides = overrides
+                        .iter()
+                        .map(|item| if item.show {
+                            t!(lang, "prerelease.override_shown", package = item.package)
+                        } else {
+                            t!(lang, "prerelease.override_hidden", package = item.package)
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ));
+            }
+            Ok(RoomMessageEventContent::notice_plain(reply.join("\n")))
+        }
+        ["prerelease", action @ ("show" | "hide")] => {
+            let show = *action == "show";
+            let packages = inv.list("packages");
+            if packages.is_empty() {
+                db.set_preference(
+                    user_id,
+                    Preference::HidePrerelease,
+                    Some((!show).to_string()),
+                )
+                .await?;
+                return Ok(RoomMessageEventContent::notice_plain(if show {
+                    t!(lang, "prerelease.will_show")
+                } else {
+                    t!(lang, "prerelease.will_hide")
+                }));
+            }
+            db.set_prerelease_overrides(
+                user_id,
+                packages
+                    .iter()
+                    .map(|package| PrereleaseOverride {
+                        package: package.clone(),
+                        show,
+                    })
+                    .collect(),
+            )
+            .await?;
+            Ok(RoomMessageEventContent::notice_plain(if show {
+                t!(
+                    lang,
+                    "prerelease.packages_will_show",
+                    packages = packages.join(", ")
+                )
+            } else {
+                t!(
+                    lang,
+                    "prerelease.packages_will_hide",
+                    packages = packages.join(", ")
+                )
+            }))
+        }
+        ["prerelease", "default"] => {
+            let packages = inv.list("packages");
+            db.clear_prerelease_overrides(user_id, packages.to_vec())
+                .await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "prerelease.default",
+                packages = packages.join(", ")
+            )))
+        }
+        ["filter", "add"] => {
+            let name = inv.arg("name").unwrap();
+            let filter = match inv.arg("expression").unwrap().parse::<Filter>() {
+                Ok(filter) => filter,
+                Err(e) => {
+                    return Ok(RoomMessageEventContent::notice_plain(t!(
+                        lang,
+                        "filter.invalid",
+                        error = e
+                    )));
+                }
+            };
+            let data = Anicca::get_local_json(data_dir).await?;
+            let matched = data.get_filter_updates(slice::from_ref(&filter))?.len();
+            db.add_filter(
+                user_id,
+                SavedFilter {
+                    name: name.to_owned(),
+                    expression: filter.to_string(),
+                },
+            )
+            .await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "filter.saved",
+                name = name,
+                updates = tn!(lang, "count.update", matched)
+            )))
+        }
+        ["filter", "list"] => {
+            let filters = db.get_filters(user_id).await?;
+            if filters.is_empty() {
+                Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "filter.none"
+                )))
+            } else {
+                Ok(RoomMessageEventContent::notice_plain(format!(
+                    "{}\n{}",
+                    t!(
+                        lang,
+                        "filter.list",
+                        filters = tn!(lang, "count.filter", filters.len())
+                    ),
+                    filters
+                        .iter()
+                        .map(|filter| format!("{}: {}", filter.name, filter.expression))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                )))
+            }
+        }
+        ["filter", "remove"] => {
+            db.remove_filters(user_id, inv.list("names").to_vec())
+                .await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "filter.removed"
+            )))
+        }
+        ["filter", "test"] => {
+            let text = inv.arg("filter").unwrap();
+            let saved = user_filters(&db, user_id).await?;
+            let filter = match saved.into_iter().find(|(name, _)| name == text) {
+                Some((_, filter)) => filter,
+                None => match text.parse::<Filter>() {
+                    Ok(filter) => filter,
+                    Err(e) => {
+                        return Ok(RoomMessageEventContent::notice_plain(t!(
+                            lang,
+                            "filter.invalid",
+                            error = e
+                        )));
+                    }
+                },
+            };
+            let data = Anicca::get_local_json(data_dir).await?;
+            let updates = data.get_filter_updates(&[filter])?;
+            if updates.is_empty() {
+                Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "updates.none"
+                )))
+            } else {
+                let (plain_updates, html_updates) = format_update_packages(lang, &updates);
+                Ok(RoomMessageEventContent::notice_html(
+                    plain_updates,
+                    html_updates,
+                ))
+            }
+        }
+        ["enable-notification"] => {
+            if let Some(tag) = tag {
+                let mut muted = muted_tags(&db, user_id).await?;
+                muted.retain(|name| *name != tag.name);
+                set_muted_tags(&db, user_id, muted).await?;
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "notification.tag_enabled",
+                    tag = tag.name
+                )));
+            }
+            if db.is_notification_enabled(user_id).await? {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "notification.already_enabled"
+                )));
+            }
+            db.enable_notification(user_id).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "notification.enabled"
+            )))
+        }
+        ["disable-notification"] => {
+            if let Some(tag) = tag {
+                let mut muted = muted_tags(&db, user_id).await?;
+                if !muted.contains(&tag.name) {
+                    muted.push(tag.name.clone());
+                }
+                set_muted_tags(&db, user_id, muted).await?;
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "notification.tag_disabled",
+                    tag = tag.name
+                )));
+            }
+            db.disable_notification(user_id).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "notification.disabled"
+            )))
+        }
+        ["+users"] => {
+            let users = db.users().await?;
+            let notification_targets = db.notification_targets().await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "users.list",
+                users = tn!(lang, "count.user", users.len()),
+                list = users
+                    .iter()
+                    .map(|id| {
+                        if notification_targets.contains(id) {
+                            format!("{} [✓]", id.as_str())
+                        } else {
+                            id.as_str().to_owned()
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )))
+        }
+        ["+list"] => {
+            let user_id = UserId::parse(inv.arg("user").unwrap())?;
+            list_reply(&db, data_dir, lang, &user_id, &view, None).await
+        }
+        ["+updates"] => {
+            let user_id = UserId::parse(inv.arg("user").unwrap())?;
+            updates_reply(&db, data_dir, lang, &user_id, &view, None).await
+        }
+        [action @ ("+subscribe" | "+unsubscribe")] => {
+            let target = UserId::parse(inv.arg("user").unwrap())?;
+            let packages = inv.list("packages").to_vec();
+            if *action == "+subscribe" {
+                let data = Anicca::get_local_json(data_dir).await?;
+                let html = match subscribe_specs(
+                    &db,
+                    data_dir,
+                    &data,
+                    lang,
+                    &target,
+                    &packages,
+                    inv.flag("force"),
+                )
+                .await?
+                {
+                    Ok((_, html)) => html,
+                    Err(reply) => return Ok(reply),
+                };
+                Ok(html_reply(format!(
+                    "{}<br/>{html}",
+                    t!(lang, "admin.subscribed", user = target)
+                )))
+            } else {
+                let list = packages.join(", ");
+                unsubscribe_packages(&db, &target, packages).await?;
+                Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "admin.unsubscribed",
+                    user = target,
+                    packages = list
+                )))
+            }
+        }
+        [action @ ("+notify-on" | "+notify-off")] => {
+            let target = UserId::parse(inv.arg("user").unwrap())?;
+            let reply = if *action == "+notify-on" {
+                db.enable_notification(&target).await?;
+                t!(lang, "admin.notify_on", user = target)
+            } else {
+                db.disable_notification(&target).await?;
+                t!(lang, "admin.notify_off", user = target)
+            };
+            Ok(RoomMessageEventContent::notice_plain(reply))
+        }
+        ["+purge"] => {
+            let target = UserId::parse(inv.arg("user").unwrap())?;
+            let preview = t!(lang, "admin.purge_preview", user = target);
+            let token = confirm::request(user_id, Action::Purge(target));
+            Ok(html_reply(format!(
+                "{preview}<br/>{}",
+                t!(
+                    lang,
+                    "admin.purge_confirm",
+                    token = token,
+                    duration = duration::format(CONFIRM_TIMEOUT)
+                )
+            )))
+        }
+        ["+broadcast"] => {
+            let message = inv.arg("message").unwrap().to_owned();
+            let recipients = match (inv.flag("targets"), inv.flag_value("collection")) {
+                (true, Some(_)) => {
+                    return Ok(usage_reply(
+                        lang,
+                        &t!(lang, "broadcast.conflict"),
+                        &inv.spec.usage(&inv.path),
+                    ));
+                }
+                (true, None) => db.notification_targets().await?,
+                (false, Some(name)) => {
+                    if !db
+                        .get_collections()
+                        .await?
+                        .iter()
+                        .any(|collection| collection.name == name)
+                    {
+                        return Ok(RoomMessageEventContent::notice_plain(t!(
+                            lang,
+                            "collection.unknown",
+                            name = name
+                        )));
+                    }
+                    db.collection_subscribers(name).await?
+                }
+                (false, None) => db.users().await?,
+            };
+            if recipients.is_empty() {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "broadcast.nobody"
+                )));
+            }
+            let preview = t!(
+                lang,
+                "broadcast.preview",
+                users = tn!(lang, "count.user", recipients.len()),
+                message = escape_html(&message).replace('\n', "<br/>")
+            );
+            let token = confirm::request(
+                user_id,
+                Action::Broadcast {
+                    recipients,
+                    message,
+                },
+            );
+            Ok(html_reply(format!(
+                "{preview}<br/>{}",
+                t!(
+                    lang,
+                    "broadcast.confirm",
+                    token = token,
+                    duration = duration::format(CONFIRM_TIMEOUT)
+                )
+            )))
+        }
+        ["+stats"] => stats_reply(&db, lang).await,
+        ["+audit"] => {
+            let count = match inv.arg("count").map(str::parse::<usize>) {
+                None => DEFAULT_AUDIT_COUNT,
+                Some(Ok(count)) if count > 0 => count,
+                Some(_) => {
+                    return Ok(usage_reply(
+                        lang,
+                        &t!(lang, "admin.invalid_count"),
+                        &inv.spec.usage(&inv.path),
+                    ));
+                }
+            };
+            audit_reply(&db, lang, count).await
+        }
+        [action @ ("+deny" | "+undeny")] => {
+            let entries = inv.list("entries").to_vec();
+            if let Some(entry) = entries.iter().find(|entry| !is_access_entry(entry)) {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "denylist.invalid",
+                    entry = entry
+                )));
+            }
+            if *action == "+deny" {
+                db.deny(entries).await?;
+            } else {
+                db.undeny(entries).await?;
+            }
+            denylist_reply(&db, lang).await
+        }
+        ["+denylist"] => denylist_reply(&db, lang).await,
+        ["+invite", "list"] => {
+            let pending = invite::pending();
+            Ok(RoomMessageEventContent::notice_plain(
+                if pending.is_empty() {
+                    t!(lang, "invite.none")
+                } else {
+                    pending
+                        .iter()
+                        .map(|item| {
+                            t!(
+                                lang,
+                                "invite.item",
+                                room = item.room_id,
+                                user = item.inviter
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                },
+            ))
+        }
+        ["+invite", action @ ("approve" | "reject")] => {
+            let room_id = RoomId::parse(inv.arg("room").unwrap())?;
+            let client = room.client();
+            // The invite stays pending until the room is known, so approving can be retried once it synced.
+            let Some(invited) = client
+                .get_room(&room_id)
+                .filter(|_| invite::take(&room_id).is_some())
+            else {
+                return Ok(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "invite.unknown",
+                    room = room_id
+                )));
+            };
+            let message = if *action == "reject" {
+                invite::reject(invited);
+                t!(lang, "invite.rejected", room = room_id)
+            } else if invite::is_full(&config.invites, client.joined_rooms().len()) {
+                invite::reject(invited);
+                t!(lang, "invite.full", room = room_id)
+            } else {
+                invite::join(invited);
+                t!(lang, "invite.approved", room = room_id)
+            };
+            Ok(RoomMessageEventContent::notice_plain(message))
+        }
+        ["+collection", action @ ("add" | "remove")] => {
+            let name = inv.arg("name").unwrap();
+            let packages = inv.list("packages").to_vec();
+            if *action == "add" {
+                db.add_to_collection(name, packages).await?;
+            } else {
+                db.remove_from_collection(name, packages).await?;
+            }
+            let packages = db
+                .get_collections()
+                .await?
+                .into_iter()
+                .find(|collection| collection.name == name)
+                .map(|collection| collection.packages)
+                .unwrap_or_default();
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "collection.updated",
+                name = name,
+                packages = tn!(lang, "count.package", packages.len()),
+                list = packages.join(", ")
+            )))
+        }
+        ["+collection", "delete"] => {
+            let name = inv.arg("name").unwrap();
+            db.delete_collection(name).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "collection.deleted",
+                name = name
+            )))
+        }
+        // Groups invoked without a subcommand.
+        _ => Ok(help_command(lang, &inv.path, inv.spec)),
+    }?;
+    // Only admin commands that ran are recorded, not the ones rejected for their arguments or failing.
+    if inv.admin {
+        log_admin_action(&db, user_id, text.trim().to_owned()).await;
+    }
+    Ok(reply)
+}
+
+/// Preview importing the subscription list a user sent as a file in a direct message.
+pub async fn handle_file(
+    client: &Client,
+    db: DatabaseImpl,
+    user_id: &UserId,
+    file: &FileMessageEventContent,
+) -> Result<RoomMessageEventContent> {
+    let lang = user_lang(&db, user_id).await?;
+    let filename = file.filename();
+    let too_large =
+        RoomMessageEventContent::notice_plain(t!(lang, "import.too_large", file = filename));
+    // Rejected before downloading when the sender states the size, and checked again after in case it lied.
+    if file
+        .info
+        .as_ref()
+        .and_then(|info| info.size)
+        .is_some_and(|size| u64::from(size) > MAX_IMPORT_SIZE as u64)
+    {
+        return Ok(too_large);
+    }
+    let data = client
+        .media()
+        .get_media_content(
+            &MediaRequestParameters {
+                source: file.source.clone(),
+                format: MediaFormat::File,
+            },
+            false,
+        )
+        .await?;
+    if data.len() > MAX_IMPORT_SIZE {
+        return Ok(too_large);
+    }
+    let export = match String::from_utf8(data)
+        .map_err(eyre::Report::from)
+        .and_then(|content| Export::parse(&content))
+    {
+        Ok(export) => export,
+        Err(e) => {
+            return Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "import.invalid",
+                file = filename,
+                error = e
+            )));
+        }
+    };
+    let plan = ImportPlan::new(&db, user_id, export).await?;
+    if plan.is_empty() {
+        return Ok(RoomMessageEventContent::notice_plain(t!(
+            lang,
+            "import.nothing",
+            file = filename
+        )));
+    }
+    let preview = format_import_preview(lang, filename, &plan);
+    let token = confirm::request(user_id, Action::Import(plan));
+    Ok(html_reply(format!(
+        "{preview}<br/>{}",
+        t!(
+            lang,
+            "import.confirm",
+            token = token,
+            duration = duration::format(CONFIRM_TIMEOUT)
+        )
+    )))
+}
+
+fn format_import_preview(lang: Lang, filename: &str, plan: &ImportPlan) -> String {
+    let mut lines = vec![t!(lang, "import.preview", file = escape_html(filename))];
+    if !plan.subscribe.is_empty() {
+        lines.push(t!(
+            lang,
+            "import.subscribe",
+            packages = code_list(plan.subscribe.iter().map(|sub| sub.to_string()))
+        ));
+    }
+    if !plan.constrain.is_empty() {
+        lines.push(t!(
+            lang,
+            "import.constrain",
+            packages = code_list(plan.constrain.iter().map(|sub| sub.to_string()))
+        ));
+    }
+    if !plan.filters.is_empty() {
+        lines.push(t!(
+            lang,
+            "import.filters",
+            names = code_list(plan.filters.iter().map(|f| &f.name))
+        ));
+    }
+    if let Some(language) = plan.language {
+        lines.push(t!(lang, "import.language", name = language.name()));
+    }
+    match plan.hide_prerelease {
+        Some(true) => lines.push(t!(lang, "import.hide_prerelease")),
+        Some(false) => lines.push(t!(lang, "import.show_prerelease")),
+        None => (),
+    }
+    if !plan.prerelease.is_empty() {
+        lines.push(t!(
+            lang,
+            "import.prerelease",
+            packages = code_list(plan.prerelease.iter().map(|item| &item.package))
+        ));
+    }
+    lines.join("<br/>- ")
+}
+
+/// Reactions confirming the action whose preview they react to.
+const CONFIRM_REACTIONS: &[&str] = &["👍", "✅", "✔"];
+
+/// Confirm a pending action if `key` is a confirming reaction to its preview, the message `event_id`.
+/// Whether reacting with `key` to the message `event_id` confirms an action of `user_id`.
+pub fn is_confirmation(user_id: &UserId, event_id: &EventId, key: &str) -> bool {
+    // Emoji may come with a variation selector.
+    CONFIRM_REACTIONS.contains(&key.trim_end_matches('\u{fe0f}'))
+        && confirm::has_preview(user_id, event_id)
+}
+
+pub async fn handle_reaction(
+    client: &Client,
+    db: DatabaseImpl,
+    user_id: &UserId,
+    event_id: &EventId,
+    key: &str,
+) -> Result<Option<RoomMessageEventContent>> {
+    if !is_confirmation(user_id, event_id, key) {
+        return Ok(None);
+    }
+    let Some(action) = confirm::take_by_preview(user_id, event_id) else {
+        return Ok(None);
+    };
+    let lang = user_lang(&db, user_id).await?;
+    apply_confirmed(client, &db, lang, user_id, action)
+        .await
+        .map(Some)
+}
+
+async fn apply_confirmed(
+    client: &Client,
+    db: &DatabaseImpl,
+    lang: Lang,
+    user_id: &UserId,
+    action: Action,
+) -> Result<RoomMessageEventContent> {
+    match action {
+        Action::Import(plan) => {
+            plan.apply(db, user_id).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "import.done"
+            )))
+        }
+        Action::Unsubscribe(packages) => {
+            let count = packages.len();
+            unsubscribe_packages(db, user_id, packages).await?;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "unsubscribe.done_bulk",
+                packages = tn!(lang, "count.package", count)
+            )))
+        }
+        Action::Purge(target) => {
+            db.purge_user(&target).await?;
+            log_admin_action(db, user_id, format!("confirm +purge {target}")).await;
+            Ok(RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "admin.purged",
+                user = target
+            )))
+        }
+        Action::Broadcast {
+            recipients,
+            message,
+        } => {
+            let report = broadcast(client.clone(), db, &recipients, &message).await;
+            log_admin_action(
+                db,
+                user_id,
+                format!(
+                    "confirm +broadcast ({} sent, {} failed) {message}",
+                    report.sent,
+                    report.failed.len()
+                ),
+            )
+            .await;
+            let mut reply = vec![t!(
+                lang,
+                "broadcast.sent",
+                users = tn!(lang, "count.user", report.sent)
+            )];
+            if !report.failed.is_empty() {
+                reply.push(t!(
+                    lang,
+                    "broadcast.failed",
+                    users = tn!(lang, "count.user", report.failed.len())
+                ));
+                reply.extend(
+                    report
+                        .failed
+                        .iter()
+                        .map(|(user_id, error)| format!("- {user_id}: {error}")),
+                );
+            }
+            Ok(RoomMessageEventContent::notice_plain(reply.join("\n")))
+        }
+    }
+}
+
+/// The user's own subscriptions whose name matches the glob `pattern` and which belong to the ABBS `section`, where
+/// given.
+async fn select_subscriptions(
+    db: &DatabaseImpl,
+    data_dir: &Path,
+    user_id: &UserId,
+    pattern: Option<&str>,
+    section: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut subscriptions = user_subscriptions(db, user_id).await?;
+    if let Some(pattern) = pattern {
+        subscriptions.retain(|sub| search::glob_matches(pattern, &sub.package));
+    }
+    if let Some(section) = section {
+        let data = Anicca::get_local_json(data_dir).await?;
+        let history = History::load(data_dir).await?;
+        subscriptions
+            .retain(|sub| Item::subscription(sub, &data, &history).section() == Some(section));
+    }
+    Ok(subscriptions.into_iter().map(|sub| sub.package).collect())
+}
+
+fn code_list(items: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+    items
+        .into_iter()
+        .map(|item| format!("<code>{}</code>", escape_html(item.as_ref())))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Drop the subscriptions to `packages` together with everything kept per subscription.
+async fn unsubscribe_packages(
+    db: &DatabaseImpl,
+    user_id: &UserId,
+    packages: Vec<String>,
+) -> Result<()> {
+    db.unsubscribe(user_id, packages.clone()).await?;
+    db.clear_constraints(user_id, packages.clone()).await?;
+    db.clear_prerelease_overrides(user_id, packages.clone())
+        .await?;
+    db.clear_delivered(user_id, packages).await
+}
+
+/// The snooze duration given as `text`, or the default one. An invalid duration is answered right away.
+fn snooze_duration(lang: Lang, text: Option<&str>) -> Result<Duration, RoomMessageEventContent> {
+    match text {
+        None => Ok(DEFAULT_SNOOZE),
+        Some(text) => duration::parse(text).ok_or_else(|| {
+            RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "snooze.invalid_duration",
+                duration = text
+            ))
+        }),
+    }
+}
+
+/// Snooze the pending updates of `packages` until `until`, or until a newer version if `None`. Returns the snoozed
+/// updates and the packages without a pending update.
+async fn snooze_updates(
+    db: &DatabaseImpl,
+    data_dir: &Path,
+    user_id: &UserId,
+    packages: &[String],
+    until: Option<u64>,
+) -> Result<(Vec<Package>, Vec<String>)> {
+    let data = Anicca::get_local_json(data_dir).await?;
+    let updates = Anicca::get_updates(&data, packages)?;
+    let not_pending = packages
+        .iter()
+        .filter(|name| !updates.iter().any(|pkg| &&pkg.name == name))
+        .cloned()
+        .collect::<Vec<String>>();
+    if !updates.is_empty() {
+        db.snooze(
+            user_id,
+            updates
+                .iter()
+                .map(|pkg| Snooze {
+                    package: pkg.name.clone(),
+                    after: pkg.after.clone(),
+                    until,
+                })
+                .collect(),
+        )
+        .await?;
+    }
+    Ok((updates, not_pending))
+}
+
+/// Report what [`snooze_updates`] did, as an acknowledgement if `snooze_for` is `None`.
+fn snoozed_reply(
+    lang: Lang,
+    updates: &[Package],
+    not_pending: &[String],
+    snooze_for: Option<Duration>,
+) -> RoomMessageEventContent {
+    let mut reply = Vec::new();
+    if !updates.is_empty() {
+        let count = tn!(lang, "count.update", updates.len());
+        reply.push(match snooze_for {
+            None => t!(lang, "ack.done", updates = count),
+            Some(snooze_for) => t!(
+                lang,
+                "snooze.done_many",
+                updates = count,
+                duration = duration::format(snooze_for)
+            ),
+        });
+    }
+    if !not_pending.is_empty() {
+        reply.push(t!(
+            lang,
+            "reply.not_pending",
+            packages = not_pending.join(", ")
+        ));
+    }
+    RoomMessageEventContent::notice_plain(reply.join("\n"))
+}
+
+/// Run a command sent as a reply to the notification `event_id` on the packages that notification listed.
+async fn handle_reply(
+    db: &DatabaseImpl,
+    data_dir: &Path,
+    lang: Lang,
+    user_id: &UserId,
+    event_id: &EventId,
+    inv: &Invocation,
+) -> Result<RoomMessageEventContent> {
+    let Some(packages) = db
+        .get_notification_log(user_id)
+        .await?
+        .into_iter()
+        .find(|notification| notification.event_id == event_id.as_str())
+        .map(|notification| notification.packages)
+    else {
+        return Ok(RoomMessageEventContent::notice_plain(t!(
+            lang,
+            "reply.unknown_notification"
+        )));
+    };
+    match inv.path.as_slice() {
+        ["unsubscribe"] => {
+            // Packages notified through collections, followed users or filters are not the user's to drop here.
+            let subscribed = db.get_packages(user_id).await?;
+            let (direct, indirect): (Vec<String>, Vec<String>) = packages
+                .into_iter()
+                .partition(|package| subscribed.contains(package));
+            let mut reply = Vec::new();
+            if !direct.is_empty() {
+                reply.push(t!(
+                    lang,
+                    "unsubscribe.done_packages",
+                    packages = direct.join(", ")
+                ));
+                unsubscribe_packages(db, user_id, direct).await?;
+            }
+            if !indirect.is_empty() {
+                reply.push(t!(
+                    lang,
+                    "unsubscribe.indirect",
+                    packages = indirect.join(", ")
+                ));
+            }
+            Ok(RoomMessageEventContent::notice_plain(reply.join("\n")))
+        }
+        ["snooze"] => {
+            let snooze_for = match snooze_duration(lang, inv.arg("duration")) {
+                Ok(snooze_for) => snooze_for,
+                Err(reply) => return Ok(reply),
+            };
+            let until = duration::now() + snooze_for.as_secs();
+            let (updates, not_pending) =
+                snooze_updates(db, data_dir, user_id, &packages, Some(until)).await?;
+            Ok(snoozed_reply(
+                lang,
+                &updates,
+                &not_pending,
+                Some(snooze_for),
+            ))
+        }
+        ["ack"] => {
+            let (updates, not_pending) =
+                snooze_updates(db, data_dir, user_id, &packages, None).await?;
+            Ok(snoozed_reply(lang, &updates, &not_pending, None))
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Denylist entries are user ids or server names.
+fn is_access_entry(entry: &str) -> bool {
+    if entry.starts_with('@') {
+        UserId::parse(entry).is_ok()
+    } else {
+        ServerName::parse(entry).is_ok()
+    }
+}
+
+/// Record an admin command in the audit log. Actions that need confirming are recorded once requested and again once
+/// carried out.
+async fn log_admin_action(db: &DatabaseImpl, admin: &UserId, command: String) {
+    if let Err(e) = db
+        .log_admin_action(AuditEntry {
+            time: duration::now(),
+            admin: admin.to_string(),
+            command: command.clone(),
+        })
+        .await
+    {
+        warn!(
+            "Unable to record `{}` of {} in the audit log: {}",
+            command, admin, e
+        );
+    }
+}
+
+/// Releases rendered from their Markdown, between a header and a footer if not empty.
+fn changelog_reply(header: &str, releases: &[Release], footer: &str) -> RoomMessageEventContent {
+    let markdown = releases
+        .iter()
+        .map(Release::to_markdown)
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    let html = FormattedBody::markdown(&markdown)
+        .map(|body| body.body)
+        .unwrap_or_else(|| escape_html(&markdown));
+    let join = |body: &str, separator: &str| {
+        [header, body, footer]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join(separator)
+    };
+    RoomMessageEventContent::notice_html(
+        html_to_plain(&join(&markdown, "\n")),
+        join(&html, "<br/>"),
+    )
+}
+
+fn unknown_version(lang: Lang, changelog: &Changelog, version: &str) -> RoomMessageEventContent {
+    let versions = changelog
+        .releases()
+        .iter()
+        .map(|release| release.version.as_str())
+        .collect::<Vec<&str>>();
+    RoomMessageEventContent::notice_plain(t!(
+        lang,
+        "changelog.unknown",
+        version = version,
+        versions = versions.join(", ")
+    ))
+}
+
+/// The pending updates of `user_id`, only those of `tag` if given.
+async fn updates_reply(
+    db: &DatabaseImpl,
+    data_dir: &Path,
+    lang: Lang,
+    user_id: &UserId,
+    view: &View,
+    tag: Option<Tag>,
+) -> Result<RoomMessageEventContent> {
+    let data = Anicca::get_local_json(data_dir).await?;
+    let mut updates = user_updates(db, user_id, &data).await?;
+    if let Some(tag) = &tag {
+        updates.retain(|pkg| tag.packages.contains(&pkg.name));
+    }
+    if updates.is_empty() {
+        return Ok(RoomMessageEventContent::notice_plain(t!(
+            lang,
+            "updates.none"
+        )));
+    }
+    let tags = match tag {
+        Some(tag) => vec![tag],
+        None => db.get_tags(user_id).await?,
+    };
+    let history = History::load(data_dir).await?;
+    let (plain_updates, html_updates) = format_updates(lang, &updates, &tags, view, Some(&history));
+    Ok(RoomMessageEventContent::notice_html(
+        plain_updates,
+        html_updates,
+    ))
+}
+
+async fn stats_reply(db: &DatabaseImpl, lang: Lang) -> Result<RoomMessageEventContent> {
+    let users = db.users().await?;
+    let notified = db.notification_targets().await?.len();
+    let mut subscriptions = 0;
+    let mut subscribers = BTreeMap::<String, usize>::new();
+    for user in &users {
+        for package in db.get_packages(user).await? {
+            subscriptions += 1;
+            *subscribers.entry(package).or_default() += 1;
+        }
+    }
+    let mut top = subscribers.iter().collect::<Vec<_>>();
+    top.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    top.truncate(STATS_TOP);
+    let collections = db.get_collections().await?.len();
+    let mut lines = vec![
+        t!(
+            lang,
+            "admin.stats_users",
+            users = tn!(lang, "count.user", users.len()),
+            notified = notified
+        ),
+        t!(
+            lang,
+            "admin.stats_subscriptions",
+            subscriptions = subscriptions,
+            packages = tn!(lang, "count.package", subscribers.len())
+        ),
+        t!(
+            lang,
+            "admin.stats_collections",
+            collections = tn!(lang, "count.collection", collections)
+        ),
+    ];
+    if !top.is_empty() {
+        lines.push(t!(
+            lang,
+            "admin.stats_top",
+            list = top
+                .iter()
+                .map(|(package, count)| format!("<code>{}</code> ({count})", escape_html(package)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+    Ok(html_reply(lines.join("<br/>")))
+}
+
+/// The latest `count` admin commands, oldest first.
+async fn audit_reply(
+    db: &DatabaseImpl,
+    lang: Lang,
+    count: usize,
+) -> Result<RoomMessageEventContent> {
+    let entries = db.get_audit_log(count).await?;
+    if entries.is_empty() {
+        return Ok(RoomMessageEventContent::notice_plain(t!(
+            lang,
+            "admin.audit_empty"
+        )));
+    }
+    let now = duration::now();
+    Ok(RoomMessageEventContent::notice_plain(
+        entries
+            .iter()
+            .map(|entry| {
+                t!(
+                    lang,
+                    "admin.audit_item",
+                    ago = duration::format(Duration::from_secs(now.saturating_sub(entry.time))),
+                    admin = entry.admin,
+                    command = entry.command
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+    ))
+}
+
+async fn denylist_reply(db: &DatabaseImpl, lang: Lang) -> Result<RoomMessageEventContent> {
+    let denylist = db.get_denylist().await?;
+    Ok(RoomMessageEventContent::notice_plain(
+        if denylist.is_empty() {
+            t!(lang, "denylist.empty")
+        } else {
+            t!(lang, "denylist.list", list = denylist.join(", "))
+        },
+    ))
+}
+
+/// Tags are single words, so they can be stored comma separated and passed to `--tag` without quotes.
+fn is_valid_tag(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_TAG_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+async fn tag_updated(
+    db: &DatabaseImpl,
+    lang: Lang,
+    user_id: &UserId,
+    name: &str,
+) -> Result<RoomMessageEventContent> {
+    let packages = db
+        .get_tags(user_id)
+        .await?
+        .into_iter()
+        .find(|tag| tag.name == name)
+        .map(|tag| tag.packages)
+        .unwrap_or_default();
+    Ok(RoomMessageEventContent::notice_plain(t!(
+        lang,
+        "tag.updated",
+        tag = name,
+        packages = tn!(lang, "count.package", packages.len()),
+        list = packages.join(", ")
+    )))
+}
+
+/// The users `user_id` follows, marking those whose list turned private.
+async fn followed_users(db: &DatabaseImpl, lang: Lang, user_id: &UserId) -> Result<Vec<String>> {
+    let mut users = Vec::new();
+    for followed in db.get_follows(user_id).await? {
+        users.push(if is_list_public(db, &followed).await? {
+            followed.to_string()
+        } else {
+            t!(lang, "follow.private_mark", user = followed)
+        });
+    }
+    Ok(users)
+}
+
+/// The subscriptions of `user_id` as [`View`] says, limited to `tag` if given. The collections and users they follow
+/// are listed after the subscriptions, unless the list is limited to a tag.
+async fn list_reply(
+    db: &DatabaseImpl,
+    data_dir: &Path,
+    lang: Lang,
+    user_id: &UserId,
+    view: &View,
+    tag: Option<&Tag>,
+) -> Result<RoomMessageEventContent> {
+    let data = Anicca::get_local_json(data_dir).await?;
+    let history = History::load(data_dir).await?;
+    let mut subscriptions = user_subscriptions(db, user_id).await?;
+    let (tags, collections, follows) = match tag {
+        Some(tag) => {
+            subscriptions.retain(|sub| tag.packages.contains(&sub.package));
+            (vec![tag.clone()], Vec::new(), Vec::new())
+        }
+        None => (
+            db.get_tags(user_id).await?,
+            db.get_collection_subscriptions(user_id).await?,
+            followed_users(db, lang, user_id).await?,
+        ),
+    };
+    if subscriptions.is_empty() && collections.is_empty() && follows.is_empty() {
+        return Ok(RoomMessageEventContent::notice_plain(t!(
+            lang,
+            "list.empty"
+        )));
+    }
+
+    let mut plain = Vec::new();
+    let mut html = Vec::new();
+    if !subscriptions.is_empty() {
+        let header = t!(
+            lang,
+            "list.header",
+            packages = tn!(lang, "count.package", subscriptions.len())
+        );
+        let items = subscriptions
+            .iter()
+            .map(|sub| Item::subscription(sub, &data, &history))
+            .collect();
+        let (plain_list, html_list) = view.render(lang, &header, items, &tags);
+        plain.push(plain_list.trim_end().to_owned());
+        html.push(html_list);
+    }
+    let mut lines = Vec::new();
+    if !collections.is_empty() {
+        lines.push(t!(
+            lang,
+            "list.collections",
+            collections = tn!(lang, "count.collection", collections.len()),
+            list = collections.join(", ")
+        ));
+    }
+    if !follows.is_empty() {
+        lines.push(t!(
+            lang,
+            "follow.list",
+            users = tn!(lang, "count.user", follows.len()),
+            list = follows.join(", ")
+        ));
+    }
+    for line in lines {
+        html.push(escape_html(&line));
+        plain.push(line);
+    }
+    Ok(RoomMessageEventContent::notice_html(
+        plain.join("\n"),
+        html.join("<br/>"),
+    ))
+}
+
+enum PackageStatus<'a> {
+    /// The package has an update in the feed, seen for `pending_for` seconds if it is in the history.
+    Pending {
+        package: &'a Package,
+        pending_for: Option<u64>,
+    },
+    /// The package only appears in the history, last at this version.
+    UpToDate(&'a str),
+}
+
+fn format_package_info(
+    lang: Lang,
+    name: &str,
+    path: &str,
+    status: PackageStatus,
+    subscribers: usize,
+    subscribed: bool,
+) -> RoomMessageEventContent {
+    let abbs_url = format!("https://github.com/AOSC-Dev/aosc-os-abbs/tree/stable/{path}");
+    let packages_url = format!("https://packages.aosc.io/packages/{name}");
+    let mut plain = vec![name.to_owned(), t!(lang, "info.path", path = path)];
+    let mut html = vec![
+        format!("<strong>{}</strong>", escape_html(name)),
+        t!(
+            lang,
+            "info.path",
+            path = format!("<a href=\"{abbs_url}\">{}</a>", escape_html(path))
+        ),
+    ];
+    match status {
+        PackageStatus::Pending {
+            package,
+            pending_for,
+        } => {
+            plain.push(t!(
+                lang,
+                "info.update",
+                before = package.before,
+                after = package.after
+            ));
+            html.push(t!(
+                lang,
+                "info.update",
+                before = format!("<code>{}</code>", escape_html(&package.before)),
+                after = format!("<code>{}</code>", escape_html(&package.after))
+            ));
+            if let Some(secs) = pending_for {
+                let line = t!(
+                    lang,
+                    "info.pending",
+                    duration = duration::format(Duration::from_secs(secs))
+                );
+                html.push(escape_html(&line));
+                plain.push(line);
+            }
+            let warnings = package
+                .warnings
+                .iter()
+                .filter(|warning| !warning.is_empty())
+                .collect::<Vec<&String>>();
+            if !warnings.is_empty() {
+                plain.push(t!(lang, "info.warnings"));
+                html.push(format!(
+                    "{}<ul>{}</ul>",
+                    t!(lang, "info.warnings"),
+                    warnings
+                        .iter()
+                        .map(|warning| format!("<li>{}</li>", escape_html(warning)))
+                        .collect::<String>()
+                ));
+                plain.extend(warnings.iter().map(|warning| format!("- {warning}")));
+            }
+        }
+        PackageStatus::UpToDate(version) => {
+            plain.push(t!(lang, "info.up_to_date", version = version));
+            html.push(t!(
+                lang,
+                "info.up_to_date",
+                version = format!("<code>{}</code>", escape_html(version))
+            ));
+        }
+    }
+    let subscription = format!(
+        "{} {}",
+        t!(
+            lang,
+            "info.subscribers",
+            users = tn!(lang, "count.user", subscribers)
+        ),
+        if subscribed {
+            t!(lang, "info.subscribed")
+        } else {
+            t!(lang, "info.not_subscribed")
+        }
+    );
+    html.push(escape_html(&subscription));
+    plain.push(subscription);
+    plain.push(format!("ABBS: {abbs_url}"));
+    plain.push(format!("packages.aosc.io: {packages_url}"));
+    html.push(format!(
+        "<a href=\"{abbs_url}\">ABBS</a> | <a href=\"{packages_url}\">packages.aosc.io</a>"
+    ));
+    RoomMessageEventContent::notice_html(plain.join("\n"), html.join("<br/>"))
+}
+
+/// Subscribe `user_id` to package specs like `llvm>=20`, leaving out the packages anicca never reported unless `force`
+/// is set. Returns the new subscriptions and the HTML describing what was done, or the reply to an invalid spec.
+async fn subscribe_specs(
+    db: &DatabaseImpl,
+    data_dir: &Path,
+    data: &Anicca,
+    lang: Lang,
+    user_id: &UserId,
+    args: &[String],
+    force: bool,
+) -> Result<Result<(Vec<Subscription>, String), RoomMessageEventContent>> {
+    let mut specs = Vec::new();
+    for arg in args {
+        match Constraint::split_spec(arg) {
+            Ok((package, constraint)) => specs.push((package.to_owned(), constraint)),
+            Err(e) => {
+                return Ok(Err(RoomMessageEventContent::notice_plain(t!(
+                    lang,
+                    "subscribe.invalid",
+                    spec = arg,
+                    error = e
+                ))));
+            }
+        }
+    }
+    let history = History::load(data_dir).await?;
+    let known = data
+        .packages()
+        .iter()
+        .map(|pkg| pkg.name.as_str())
+        .chain(history.names())
+        .collect::<BTreeSet<&str>>();
+    let (specs, unknown): (Vec<_>, Vec<_>) = specs
+        .into_iter()
+        .partition(|(package, _)| force || known.contains(package.as_str()));
+    let unknown = unknown
+        .into_iter()
+        .map(|(package, _)| {
+            let suggestions = search::suggest(&package, known.iter().copied(), SUGGESTION_LIMIT);
+            (package, suggestions)
+        })
+        .collect::<Vec<_>>();
+
+    let subscribed = db.get_packages(user_id).await?;
+    let (already, new): (Vec<Subscription>, Vec<Subscription>) = specs
+        .iter()
+        .map(|(package, constraint)| Subscription {
+            package: package.clone(),
+            constraint: constraint.clone(),
+        })
+        .partition(|sub| subscribed.contains(&sub.package));
+    db.subscribe(user_id, new.iter().map(|sub| sub.package.clone()).collect())
+        .await?;
+    let (constrained, unconstrained): (Vec<_>, Vec<_>) = specs
+        .into_iter()
+        .partition(|(_, constraint)| constraint.is_some());
+    db.set_constraints(
+        user_id,
+        constrained
+            .into_iter()
+            .map(|(package, constraint)| PackageConstraint {
+                package,
+                constraint: constraint.unwrap().to_string(),
+            })
+            .collect(),
+    )
+    .await?;
+    db.clear_constraints(
+        user_id,
+        unconstrained
+            .into_iter()
+            .map(|(package, _)| package)
+            .collect(),
+    )
+    .await?;
+    let html = format_subscribe_result(lang, &new, &already, &unknown);
+    Ok(Ok((new, html)))
+}
+
+fn format_subscribe_result(
+    lang: Lang,
+    new: &[Subscription],
+    already: &[Subscription],
+    unknown: &[(String, Vec<&str>)],
+) -> String {
+    let join = |subs: &[Subscription]| {
+        subs.iter()
+            .map(|sub| format!("<code>{}</code>", escape_html(&sub.to_string())))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let mut lines = Vec::new();
+    if !new.is_empty() {
+        lines.push(t!(lang, "subscribe.new", packages = join(new)));
+    }
+    if !already.is_empty() {
+        lines.push(t!(lang, "subscribe.already", packages = join(already)));
+    }
+    for (package, suggestions) in unknown {
+        let package = format!("<code>{}</code>", escape_html(package));
+        if suggestions.is_empty() {
+            lines.push(t!(lang, "subscribe.unknown", package = package));
+        } else {
+            lines.push(t!(
+                lang,
+                "subscribe.did_you_mean",
+                package = package,
+                suggestions = suggestions
+                    .iter()
+                    .map(|name| format!("<code>{}</code>", escape_html(name)))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+    }
+    if !unknown.is_empty() {
+        lines.push(t!(lang, "subscribe.force_hint"));
+    }
+    lines.join("<br/>")
+}
+
+fn format_search_results(lang: Lang, query: &str, results: &[&Package]) -> RoomMessageEventContent {
+    if results.is_empty() {
+        return RoomMessageEventContent::notice_plain(t!(lang, "search.none", query = query));
+    }
+    let header = t!(
+        lang,
+        "search.found",
+        packages = tn!(lang, "count.package", results.len()),
+        query = query
+    );
+    let shown = &results[..results.len().min(SEARCH_LIMIT)];
+    let mut plain = format!("{header}:\n");
+    let mut html = format!("<strong>{}</strong><br/><ul>", escape_html(&header));
+    for pkg in shown {
+        plain.push_str(&format!(
+            "- {} ({}): {} -> {}\n",
+            pkg.name, pkg.path, pkg.before, pkg.after
+        ));
+        html.push_str(&format!(
+            "<li><code>{}</code> ({}): <code>{} -> {}</code></li>",
+            escape_html(&pkg.name),
+            escape_html(&pkg.path),
+            escape_html(&pkg.before),
+            escape_html(&pkg.after)
+        ));
+    }
+    html.push_str("</ul>");
+    if shown.len() < results.len() {
+        let truncated = t!(lang, "search.truncated", count = shown.len());
+        plain.push_str(&truncated);
+        html.push_str(&escape_html(&truncated));
+    }
+    RoomMessageEventContent::notice_html(plain.trim_end().to_owned(), html)
+}
@@ -0,0 +1,548 @@
+//! Declarative command definitions and the parser turning a message into an [`Invocation`].
+//!
+//! Words are separated by whitespace; single or double quotes group words together and `\` escapes the next
+//! character. Words starting with `--` are flags unless quoted or placed after a bare `--`.
+
+use std::{collections::HashMap, fmt};
+
+use super::COMMAND_PREFIX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// A single word.
+    Single,
+    /// Every remaining word, each further split on commas.
+    List,
+    /// The rest of the message verbatim, quotes included.
+    Raw,
+}
+
+#[derive(Debug)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+impl ArgSpec {
+    pub const fn required(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: ArgKind::Single,
+            required: true,
+        }
+    }
+
+    pub const fn optional(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: ArgKind::Single,
+            required: false,
+        }
+    }
+
+    pub const fn list(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: ArgKind::List,
+            required: true,
+        }
+    }
+
+    pub const fn optional_list(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: ArgKind::List,
+            required: false,
+        }
+    }
+
+    pub const fn raw(name: &'static str) -> Self {
+        Self {
+            name,
+            kind: ArgKind::Raw,
+            required: true,
+        }
+    }
+}
+
+impl fmt::Display for ArgSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, self.required) {
+            (ArgKind::List, true) => write!(f, "<{}...>", self.name),
+            (ArgKind::List, false) => write!(f, "[{}...]", self.name),
+            (_, true) => write!(f, "<{}>", self.name),
+            (_, false) => write!(f, "[{}]", self.name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FlagSpec {
+    pub name: &'static str,
+    /// Name of the value the flag takes, if any.
+    pub value: Option<&'static str>,
+    pub summary: &'static str,
+}
+
+impl FlagSpec {
+    pub const fn switch(name: &'static str, summary: &'static str) -> Self {
+        Self {
+            name,
+            value: None,
+            summary,
+        }
+    }
+
+    pub const fn value(name: &'static str, value: &'static str, summary: &'static str) -> Self {
+        Self {
+            name,
+            value: Some(value),
+            summary,
+        }
+    }
+}
+
+impl fmt::Display for FlagSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(value) => write!(f, "--{} <{}>", self.name, value),
+            None => write!(f, "--{}", self.name),
+        }
+    }
+}
+
+/// Every command accepts `--help` to show its own help instead of running.
+const HELP_FLAG: FlagSpec = FlagSpec::switch("help", "flag.help");
+
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Empty for groups that do nothing on their own besides dispatching to subcommands.
+    pub summary: &'static str,
+    pub args: &'static [ArgSpec],
+    pub flags: &'static [FlagSpec],
+    pub subcommands: &'static [CommandSpec],
+    /// Only usable by admins in a direct chat or the configured admin room, and hidden from everyone else.
+    pub admin: bool,
+}
+
+impl CommandSpec {
+    pub const fn new(name: &'static str, summary: &'static str) -> Self {
+        Self {
+            name,
+            summary,
+            args: &[],
+            flags: &[],
+            subcommands: &[],
+            admin: false,
+        }
+    }
+
+    pub const fn args(self, args: &'static [ArgSpec]) -> Self {
+        Self { args, ..self }
+    }
+
+    pub const fn flags(self, flags: &'static [FlagSpec]) -> Self {
+        Self { flags, ..self }
+    }
+
+    pub const fn subcommands(self, subcommands: &'static [CommandSpec]) -> Self {
+        Self {
+            subcommands,
+            ..self
+        }
+    }
+
+    pub const fn admin(self) -> Self {
+        Self {
+            admin: true,
+            ..self
+        }
+    }
+
+    fn find<'a>(commands: &'a [CommandSpec], name: &str) -> Option<&'a CommandSpec> {
+        commands.iter().find(|spec| spec.name == name)
+    }
+
+    /// Walk down `path` from the top level commands.
+    pub fn resolve<'a>(
+        commands: &'static [CommandSpec],
+        path: impl IntoIterator<Item = &'a str>,
+    ) -> Option<(Vec<&'static str>, &'static CommandSpec)> {
+        let mut names = Vec::new();
+        let mut candidates = commands;
+        let mut spec = None;
+        for name in path {
+            let found = Self::find(candidates, name)?;
+            names.push(found.name);
+            candidates = found.subcommands;
+            spec = Some(found);
+        }
+        spec.map(|spec| (names, spec))
+    }
+
+    /// One line usage like `!anic filter add <name> <expression>`.
+    pub fn usage(&self, path: &[&str]) -> String {
+        let mut usage = format!("{COMMAND_PREFIX} {}", path.join(" "));
+        if self.args.is_empty() && !self.subcommands.is_empty() {
+            let names = self
+                .subcommands
+                .iter()
+                .map(|spec| spec.name)
+                .collect::<Vec<&str>>();
+            usage.push_str(&format!(" <{}>", names.join("|")));
+        }
+        for arg in self.args {
+            usage.push_str(&format!(" {arg}"));
+        }
+        for flag in self.flags {
+            usage.push_str(&format!(" [{flag}]"));
+        }
+        usage
+    }
+
+    /// Usage lines of this command and all of its subcommands, paired with their summaries.
+    pub fn usages(&self, path: &mut Vec<&'static str>) -> Vec<(String, &'static str)> {
+        path.push(self.name);
+        let mut lines = Vec::new();
+        if !self.summary.is_empty() {
+            lines.push((self.usage(path), self.summary));
+        }
+        for subcommand in self.subcommands {
+            lines.extend(subcommand.usages(path));
+        }
+        path.pop();
+        lines
+    }
+}
+
+/// A successfully parsed command.
+#[derive(Debug)]
+pub struct Invocation {
+    pub path: Vec<&'static str>,
+    pub spec: &'static CommandSpec,
+    /// Whether the top level command is an admin command.
+    pub admin: bool,
+    args: HashMap<&'static str, Vec<String>>,
+    flags: HashMap<&'static str, Option<String>>,
+}
+
+impl Invocation {
+    /// The first value of an argument.
+    pub fn arg(&self, name: &str) -> Option<&str> {
+        self.args
+            .get(name)
+            .and_then(|values| values.first())
+            .map(|value| value.as_str())
+    }
+
+    /// All values of a list argument.
+    pub fn list(&self, name: &str) -> &[String] {
+        self.args.get(name).map_or(&[], |values| values.as_slice())
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains_key(name)
+    }
+
+    /// The value given to a flag that takes one.
+    pub fn flag_value(&self, name: &str) -> Option<&str> {
+        self.flags.get(name).and_then(|value| value.as_deref())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    Empty,
+    UnterminatedQuote,
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    UnexpectedArgument(String),
+    UnknownFlag(String),
+    MissingFlagValue(&'static str),
+    UnexpectedFlagValue(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Usage of the command being parsed, once it is known.
+    pub usage: Option<String>,
+}
+
+impl From<ParseErrorKind> for ParseError {
+    fn from(kind: ParseErrorKind) -> Self {
+        Self { kind, usage: None }
+    }
+}
+
+#[derive(Debug)]
+struct Word {
+    text: String,
+    /// Byte offset of the word in the original message.
+    start: usize,
+    quoted: bool,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Word>, ParseErrorKind> {
+    let mut words = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        let mut quoted = false;
+        let mut quote = None;
+        while let Some(&(_, c)) = chars.peek() {
+            if quote.is_none() && c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            match (quote, c) {
+                (_, '\\') => {
+                    if let Some((_, escaped)) = chars.next() {
+                        word.push(escaped);
+                    }
+                }
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    quoted = true;
+                }
+                (Some(q), c) if c == q => quote = None,
+                (_, c) => word.push(c),
+            }
+        }
+        if quote.is_some() {
+            return Err(ParseErrorKind::UnterminatedQuote);
+        }
+        words.push(Word {
+            text: word,
+            start,
+            quoted,
+        });
+    }
+    Ok(words)
+}
+
+/// Parse a message with the command prefix already stripped.
+pub fn parse(commands: &'static [CommandSpec], text: &str) -> Result<Invocation, ParseError> {
+    let words = tokenize(text)?;
+    let mut words = words.iter().peekable();
+    let first = words.next().ok_or(ParseErrorKind::Empty)?;
+    let root = CommandSpec::find(commands, &first.text)
+        .ok_or_else(|| ParseErrorKind::UnknownCommand(first.text.clone()))?;
+
+    let mut spec = root;
+    let mut path = vec![root.name];
+    while let Some(word) = words.peek()
+        && !word.quoted
+        && let Some(subcommand) = CommandSpec::find(spec.subcommands, &word.text)
+    {
+        spec = subcommand;
+        path.push(subcommand.name);
+        words.next();
+    }
+
+    let error = |kind| ParseError {
+        kind,
+        usage: Some(spec.usage(&path)),
+    };
+    let mut args: HashMap<&'static str, Vec<String>> = HashMap::new();
+    let mut flags = HashMap::new();
+    let mut positional = spec.args.iter().peekable();
+    let mut flags_done = false;
+    while let Some(word) = words.next() {
+        if !flags_done && !word.quoted && word.text == "--" {
+            flags_done = true;
+            continue;
+        }
+        if !flags_done
+            && !word.quoted
+            && let Some(flag) = word.text.strip_prefix("--")
+        {
+            let (name, inline) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (flag, None),
+            };
+            let flag = spec
+                .flags
+                .iter()
+                .chain([&HELP_FLAG])
+                .find(|flag| flag.name == name)
+                .ok_or_else(|| error(ParseErrorKind::UnknownFlag(name.to_owned())))?;
+            let value = match (flag.value, inline) {
+                (None, None) => None,
+                (None, Some(_)) => {
+                    return Err(error(ParseErrorKind::UnexpectedFlagValue(flag.name)));
+                }
+                (Some(_), Some(value)) => Some(value),
+                (Some(_), None) => Some(
+                    words
+                        .next()
+                        .map(|word| word.text.clone())
+                        .ok_or_else(|| error(ParseErrorKind::MissingFlagValue(flag.name)))?,
+                ),
+            };
+            flags.insert(flag.name, value);
+            continue;
+        }
+
+        let Some(arg) = positional.peek() else {
+            return Err(error(ParseErrorKind::UnexpectedArgument(word.text.clone())));
+        };
+        match arg.kind {
+            ArgKind::Single => {
+                args.insert(arg.name, vec![word.text.clone()]);
+                positional.next();
+            }
+            ArgKind::List if word.quoted => {
+                args.entry(arg.name).or_default().push(word.text.clone())
+            }
+            ArgKind::List => args.entry(arg.name).or_default().extend(
+                word.text
+                    .split(',')
+                    .filter(|item| !item.is_empty())
+                    .map(|item| item.to_owned()),
+            ),
+            ArgKind::Raw => {
+                args.insert(arg.name, vec![text[word.start..].trim().to_owned()]);
+                break;
+            }
+        }
+    }
+
+    if !flags.contains_key(HELP_FLAG.name)
+        && let Some(arg) = spec
+            .args
+            .iter()
+            .find(|arg| arg.required && args.get(arg.name).is_none_or(|values| values.is_empty()))
+    {
+        return Err(error(ParseErrorKind::MissingArgument(arg.name)));
+    }
+
+    Ok(Invocation {
+        path,
+        spec,
+        admin: root.admin,
+        args,
+        flags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static COMMANDS: &[CommandSpec] = &[
+        CommandSpec::new("subscribe", "Subscribe")
+            .args(&[ArgSpec::list("packages")])
+            .flags(&[
+                FlagSpec::switch("force", "Force"),
+                FlagSpec::value("tag", "tag", "Tag"),
+            ]),
+        CommandSpec::new("snooze", "Snooze")
+            .args(&[ArgSpec::required("package"), ArgSpec::optional("duration")]),
+        CommandSpec::new("filter", "").subcommands(&[
+            CommandSpec::new("add", "Add")
+                .args(&[ArgSpec::required("name"), ArgSpec::raw("expression")]),
+            CommandSpec::new("list", "List"),
+        ]),
+    ];
+
+    fn kind(text: &str) -> ParseErrorKind {
+        parse(COMMANDS, text).unwrap_err().kind
+    }
+
+    #[test]
+    fn test_parse() {
+        let inv = parse(COMMANDS, "subscribe foo,bar 'baz,qux' --force").unwrap();
+        assert_eq!(inv.path, ["subscribe"]);
+        assert_eq!(inv.list("packages"), ["foo", "bar", "baz,qux"]);
+        assert!(inv.flag("force"));
+        assert_eq!(inv.flag_value("tag"), None);
+
+        let inv = parse(COMMANDS, "subscribe foo --tag gui --tag=cli").unwrap();
+        assert_eq!(inv.list("packages"), ["foo"]);
+        assert_eq!(inv.flag_value("tag"), Some("cli"));
+
+        let inv = parse(COMMANDS, "snooze -- --weird").unwrap();
+        assert_eq!(inv.arg("package"), Some("--weird"));
+        assert_eq!(inv.arg("duration"), None);
+
+        let inv = parse(
+            COMMANDS,
+            "filter add lang path ^= \"lang-\" and not prerelease",
+        )
+        .unwrap();
+        assert_eq!(inv.path, ["filter", "add"]);
+        assert_eq!(inv.arg("name"), Some("lang"));
+        assert_eq!(
+            inv.arg("expression"),
+            Some("path ^= \"lang-\" and not prerelease")
+        );
+
+        let inv = parse(COMMANDS, "filter").unwrap();
+        assert_eq!(inv.path, ["filter"]);
+        assert!(parse(COMMANDS, "snooze --help").unwrap().flag("help"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(kind("   "), ParseErrorKind::Empty);
+        assert_eq!(
+            kind("nope"),
+            ParseErrorKind::UnknownCommand("nope".to_owned())
+        );
+        assert_eq!(
+            kind("subscribe"),
+            ParseErrorKind::MissingArgument("packages")
+        );
+        assert_eq!(kind("subscribe \"foo"), ParseErrorKind::UnterminatedQuote);
+        assert_eq!(
+            kind("subscribe foo --all"),
+            ParseErrorKind::UnknownFlag("all".to_owned())
+        );
+        assert_eq!(
+            kind("subscribe foo --force=yes"),
+            ParseErrorKind::UnexpectedFlagValue("force")
+        );
+        assert_eq!(
+            kind("subscribe foo --tag"),
+            ParseErrorKind::MissingFlagValue("tag")
+        );
+        assert_eq!(
+            kind("snooze a 1d 2d"),
+            ParseErrorKind::UnexpectedArgument("2d".to_owned())
+        );
+        assert_eq!(
+            kind("filter list x"),
+            ParseErrorKind::UnexpectedArgument("x".to_owned())
+        );
+        assert_eq!(
+            parse(COMMANDS, "filter add x")
+                .unwrap_err()
+                .usage
+                .as_deref(),
+            Some("!anic filter add <name> <expression>")
+        );
+    }
+
+    #[test]
+    fn test_usages() {
+        let lines = COMMANDS[2].usages(&mut Vec::new());
+        assert_eq!(
+            lines
+                .iter()
+                .map(|(usage, _)| usage.as_str())
+                .collect::<Vec<&str>>(),
+            ["!anic filter add <name> <expression>", "!anic filter list"]
+        );
+        assert_eq!(
+            COMMANDS[0].usage(&["subscribe"]),
+            "!anic subscribe <packages...> [--force] [--tag <tag>]"
+        );
+    }
+}
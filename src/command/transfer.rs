@@ -0,0 +1,356 @@
+//! Subscription lists exported to and imported from file attachments.
+//!
+//! A list is either the JSON form of [`Export`] or plain text with one entry per line:
+//!
+//! ```text
+//! # Lines starting with `#` are comments.
+//! llvm>=20 openssl@major
+//! filter rust = path ^= "lang-rust"
+//! language = zh-CN
+//! hide_prerelease = true
+//! prerelease llvm = show
+//! ```
+//!
+//! Lines without ` = ` hold subscriptions separated by whitespace or commas, so plain package lists import as is.
+
+use anicca_subscribe::{anicca::Subscription, filter::Filter, version::Constraint};
+use eyre::{Result, bail, eyre};
+use matrix_sdk::ruma::UserId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    bot::{user_lang, user_subscriptions},
+    db::*,
+    i18n::Lang,
+};
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct Export {
+    /// Subscriptions including their constraints, e.g. `llvm>=20`.
+    #[serde(default)]
+    pub subscriptions: Vec<String>,
+    #[serde(default)]
+    pub filters: Vec<ExportedFilter>,
+    #[serde(default)]
+    pub preferences: ExportedPreferences,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExportedFilter {
+    pub name: String,
+    pub expression: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ExportedPreferences {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hide_prerelease: Option<bool>,
+    /// Whether pre-release updates of a package are shown, regardless of `hide_prerelease`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prerelease: BTreeMap<String, bool>,
+}
+
+impl Export {
+    pub async fn collect(db: &DatabaseImpl, user_id: &UserId) -> Result<Self> {
+        let language = db.get_preference(user_id, Preference::Language).await?;
+        let hide_prerelease = db
+            .get_preference(user_id, Preference::HidePrerelease)
+            .await?
+            .map(|value| value == "true");
+        Ok(Self {
+            subscriptions: user_subscriptions(db, user_id)
+                .await?
+                .iter()
+                .map(|sub| sub.to_string())
+                .collect(),
+            filters: db
+                .get_filters(user_id)
+                .await?
+                .into_iter()
+                .map(|saved| ExportedFilter {
+                    name: saved.name,
+                    expression: saved.expression,
+                })
+                .collect(),
+            preferences: ExportedPreferences {
+                language,
+                hide_prerelease,
+                prerelease: db
+                    .get_prerelease_overrides(user_id)
+                    .await?
+                    .into_iter()
+                    .map(|item| (item.package, item.show))
+                    .collect(),
+            },
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec!["# anicca-subscribe subscription list".to_owned()];
+        lines.extend(self.subscriptions.iter().cloned());
+        for filter in &self.filters {
+            lines.push(format!("filter {} = {}", filter.name, filter.expression));
+        }
+        let preferences = &self.preferences;
+        if let Some(language) = &preferences.language {
+            lines.push(format!("language = {language}"));
+        }
+        if let Some(hide) = preferences.hide_prerelease {
+            lines.push(format!("hide_prerelease = {hide}"));
+        }
+        for (package, show) in &preferences.prerelease {
+            let value = if *show { "show" } else { "hide" };
+            lines.push(format!("prerelease {package} = {value}"));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Read a list in either format, JSON being recognized by its leading brace, and check every entry.
+    pub fn parse(content: &str) -> Result<Self> {
+        let export = if content.trim_start().starts_with('{') {
+            serde_json::from_str(content)?
+        } else {
+            Self::parse_text(content)?
+        };
+        export.validate()?;
+        Ok(export)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for spec in &self.subscriptions {
+            Constraint::split_spec(spec)?;
+        }
+        for filter in &self.filters {
+            filter
+                .expression
+                .parse::<Filter>()
+                .map_err(|e| eyre!("filter {}: {}", filter.name, e))?;
+        }
+        if let Some(code) = &self.preferences.language {
+            code.parse::<Lang>()?;
+        }
+        Ok(())
+    }
+
+    fn parse_text(content: &str) -> Result<Self> {
+        let mut export = Self::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let result = match line.split_once(" = ") {
+                None => {
+                    export.subscriptions.extend(
+                        line.split(|c: char| c.is_whitespace() || c == ',')
+                            .filter(|spec| !spec.is_empty())
+                            .map(|spec| spec.to_owned()),
+                    );
+                    Ok(())
+                }
+                Some((key, value)) => export.set(key.trim(), value.trim()),
+            };
+            result.map_err(|e| eyre!("line {}: {}", i + 1, e))?;
+        }
+        Ok(export)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let preferences = &mut self.preferences;
+        match key.split_once(char::is_whitespace) {
+            Some(("filter", name)) => self.filters.push(ExportedFilter {
+                name: name.trim().to_owned(),
+                expression: value.to_owned(),
+            }),
+            Some(("prerelease", package)) => {
+                let show = match value {
+                    "show" => true,
+                    "hide" => false,
+                    _ => bail!("expected show or hide, found {value}"),
+                };
+                preferences
+                    .prerelease
+                    .insert(package.trim().to_owned(), show);
+            }
+            None if key == "language" => preferences.language = Some(value.to_owned()),
+            None if key == "hide_prerelease" => {
+                preferences.hide_prerelease = Some(
+                    value
+                        .parse()
+                        .map_err(|_| eyre!("expected true or false, found {value}"))?,
+                )
+            }
+            _ => bail!("unknown setting {key}"),
+        }
+        Ok(())
+    }
+}
+
+/// The changes importing a list makes. Imports only add to or update what the user has, they never unsubscribe.
+#[derive(Debug, Default)]
+pub struct ImportPlan {
+    pub subscribe: Vec<Subscription>,
+    /// Subscriptions that are kept but get another constraint.
+    pub constrain: Vec<Subscription>,
+    pub filters: Vec<SavedFilter>,
+    pub language: Option<Lang>,
+    pub hide_prerelease: Option<bool>,
+    pub prerelease: Vec<PrereleaseOverride>,
+}
+
+impl ImportPlan {
+    /// Compare a parsed `export` against what the user has.
+    pub async fn new(db: &DatabaseImpl, user_id: &UserId, export: Export) -> Result<Self> {
+        let mut plan = Self::default();
+
+        let current = user_subscriptions(db, user_id).await?;
+        for spec in &export.subscriptions {
+            let (package, constraint) = Constraint::split_spec(spec)?;
+            let sub = Subscription {
+                package: package.to_owned(),
+                constraint,
+            };
+            match current.iter().find(|old| old.package == sub.package) {
+                None if !plan.subscribe.contains(&sub) => plan.subscribe.push(sub),
+                Some(old) if old.constraint != sub.constraint => plan.constrain.push(sub),
+                _ => (),
+            }
+        }
+
+        let filters = db.get_filters(user_id).await?;
+        for filter in export.filters {
+            let saved = SavedFilter {
+                name: filter.name,
+                expression: filter.expression,
+            };
+            if !filters.contains(&saved) {
+                plan.filters.push(saved);
+            }
+        }
+
+        let preferences = export.preferences;
+        if let Some(code) = preferences.language {
+            let language = code.parse::<Lang>()?;
+            if language != user_lang(db, user_id).await? {
+                plan.language = Some(language);
+            }
+        }
+        if let Some(hide) = preferences.hide_prerelease {
+            let current = db
+                .get_preference(user_id, Preference::HidePrerelease)
+                .await?
+                .is_some_and(|value| value == "true");
+            if hide != current {
+                plan.hide_prerelease = Some(hide);
+            }
+        }
+        let overrides = db.get_prerelease_overrides(user_id).await?;
+        for (package, show) in preferences.prerelease {
+            let item = PrereleaseOverride { package, show };
+            if !overrides.contains(&item) {
+                plan.prerelease.push(item);
+            }
+        }
+
+        Ok(plan)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribe.is_empty()
+            && self.constrain.is_empty()
+            && self.filters.is_empty()
+            && self.language.is_none()
+            && self.hide_prerelease.is_none()
+            && self.prerelease.is_empty()
+    }
+
+    pub async fn apply(self, db: &DatabaseImpl, user_id: &UserId) -> Result<()> {
+        db.subscribe(
+            user_id,
+            self.subscribe
+                .iter()
+                .map(|sub| sub.package.clone())
+                .collect(),
+        )
+        .await?;
+        let (constrained, unconstrained): (Vec<_>, Vec<_>) = self
+            .subscribe
+            .into_iter()
+            .chain(self.constrain)
+            .partition(|sub| sub.constraint.is_some());
+        db.set_constraints(
+            user_id,
+            constrained
+                .into_iter()
+                .map(|sub| PackageConstraint {
+                    package: sub.package,
+                    constraint: sub.constraint.unwrap().to_string(),
+                })
+                .collect(),
+        )
+        .await?;
+        db.clear_constraints(
+            user_id,
+            unconstrained.into_iter().map(|sub| sub.package).collect(),
+        )
+        .await?;
+        for filter in self.filters {
+            db.add_filter(user_id, filter).await?;
+        }
+        if let Some(language) = self.language {
+            db.set_preference(
+                user_id,
+                Preference::Language,
+                Some(language.code().to_owned()),
+            )
+            .await?;
+        }
+        if let Some(hide) = self.hide_prerelease {
+            db.set_preference(user_id, Preference::HidePrerelease, Some(hide.to_string()))
+                .await?;
+        }
+        db.set_prerelease_overrides(user_id, self.prerelease)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let export = Export {
+            subscriptions: vec!["llvm>=20".to_owned(), "openssl@major".to_owned()],
+            filters: vec![ExportedFilter {
+                name: "rust".to_owned(),
+                expression: "path ^= \"lang-rust\"".to_owned(),
+            }],
+            preferences: ExportedPreferences {
+                language: Some("zh-CN".to_owned()),
+                hide_prerelease: Some(true),
+                prerelease: BTreeMap::from([("llvm".to_owned(), true)]),
+            },
+        };
+        assert_eq!(Export::parse(&export.to_text()).unwrap(), export);
+        assert_eq!(Export::parse(&export.to_json().unwrap()).unwrap(), export);
+
+        let export = Export::parse("# my list\nfoo, bar\n\nbaz\n").unwrap();
+        assert_eq!(export.subscriptions, ["foo", "bar", "baz"]);
+        assert_eq!(
+            Export::parse("foo\ncolor = blue").unwrap_err().to_string(),
+            "line 2: unknown setting color"
+        );
+        assert!(Export::parse("prerelease foo = maybe").is_err());
+        assert!(Export::parse("foo>=").is_err());
+        assert!(Export::parse("language = fr").is_err());
+    }
+}
@@ -0,0 +1,427 @@
+//! Sorting, grouping and paging the package lists of `list` and `updates`.
+//!
+//! Lists are rendered as HTML and turned into plain text by swapping the few tags used, so both forms always show
+//! the same rows, headings and page line.
+
+use anicca_subscribe::{
+    anicca::{Anicca, Package, Subscription},
+    history::History,
+    version::{BumpKind, Version},
+};
+use std::{cmp::Reverse, collections::BTreeMap, str::FromStr};
+
+use super::escape_html;
+use crate::{
+    bot::group_by_tag,
+    db::Tag,
+    i18n::{Lang, t, tn},
+};
+
+pub const DEFAULT_PER_PAGE: usize = 50;
+pub const MAX_PER_PAGE: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Path,
+    /// Largest version bump first.
+    Bump,
+    /// Longest pending update first.
+    Age,
+}
+
+impl FromStr for SortKey {
+    type Err = ViewError;
+
+    fn from_str(s: &str) -> Result<Self, ViewError> {
+        match s {
+            "name" => Ok(Self::Name),
+            "path" => Ok(Self::Path),
+            "bump" => Ok(Self::Bump),
+            "age" => Ok(Self::Age),
+            _ => Err(ViewError::Sort(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Grouping {
+    /// One group per tag of the user, then the untagged packages.
+    #[default]
+    Tag,
+    /// One group per ABBS section, the first component of the package path.
+    Section,
+    None,
+}
+
+impl FromStr for Grouping {
+    type Err = ViewError;
+
+    fn from_str(s: &str) -> Result<Self, ViewError> {
+        match s {
+            "tag" => Ok(Self::Tag),
+            "section" => Ok(Self::Section),
+            "none" => Ok(Self::None),
+            _ => Err(ViewError::Group(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViewError {
+    Sort(String),
+    Group(String),
+    /// A flag that needs a positive number, and what it got instead.
+    Number(&'static str, String),
+}
+
+impl ViewError {
+    pub fn message(&self, lang: Lang) -> String {
+        match self {
+            Self::Sort(key) => t!(lang, "view.invalid_sort", key = key),
+            Self::Group(mode) => t!(lang, "view.invalid_group", mode = mode),
+            Self::Number(flag, value) => t!(
+                lang,
+                "view.invalid_number",
+                flag = flag,
+                value = value,
+                max = MAX_PER_PAGE
+            ),
+        }
+    }
+}
+
+/// How a list is sorted, grouped and paged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View {
+    pub sort: SortKey,
+    pub group: Grouping,
+    /// Starting from 1.
+    pub page: usize,
+    pub per_page: usize,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            sort: SortKey::default(),
+            group: Grouping::default(),
+            page: 1,
+            per_page: DEFAULT_PER_PAGE,
+        }
+    }
+}
+
+impl View {
+    /// Everything on one page, as notifications are sent.
+    pub fn all() -> Self {
+        Self {
+            per_page: usize::MAX,
+            ..Self::default()
+        }
+    }
+
+    /// Read the values of the `--sort`, `--group`, `--page` and `--per-page` flags, falling back to the defaults for
+    /// missing ones.
+    pub fn parse(
+        sort: Option<&str>,
+        group: Option<&str>,
+        page: Option<&str>,
+        per_page: Option<&str>,
+    ) -> Result<Self, ViewError> {
+        let number = |flag: &'static str, value: &str, max: usize| match value.parse::<usize>() {
+            Ok(number) if (1..=max).contains(&number) => Ok(number),
+            _ => Err(ViewError::Number(flag, value.to_owned())),
+        };
+        let mut view = Self::default();
+        if let Some(sort) = sort {
+            view.sort = sort.parse()?;
+        }
+        if let Some(group) = group {
+            view.group = group.parse()?;
+        }
+        if let Some(page) = page {
+            view.page = number("page", page, usize::MAX)?;
+        }
+        if let Some(per_page) = per_page {
+            view.per_page = number("per-page", per_page, MAX_PER_PAGE)?;
+        }
+        Ok(view)
+    }
+
+    /// Sort `items` and pick the ones on the requested page, which is clamped to the last one.
+    fn arrange(&self, mut items: Vec<Item>) -> Page {
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        match self.sort {
+            SortKey::Name => (),
+            // Stable sorts keep packages with equal keys in name order. Packages without a key go last.
+            SortKey::Path => {
+                items.sort_by(|a, b| (a.path.is_none(), &a.path).cmp(&(b.path.is_none(), &b.path)))
+            }
+            SortKey::Bump => items.sort_by_key(|item| Reverse(item.bump)),
+            SortKey::Age => items.sort_by_key(|item| Reverse(item.age)),
+        }
+        let total = items.len();
+        let pages = total.div_ceil(self.per_page).max(1);
+        let page = self.page.min(pages);
+        let items = items
+            .into_iter()
+            .skip((page - 1).saturating_mul(self.per_page))
+            .take(self.per_page)
+            .collect();
+        Page {
+            items,
+            page,
+            pages,
+            total,
+        }
+    }
+
+    fn groups<'a>(
+        &self,
+        items: &'a [Item],
+        tags: &'a [Tag],
+    ) -> Vec<(Option<String>, Vec<&'a Item>)> {
+        match self.group {
+            Grouping::Tag => group_by_tag(items, |item| &item.name, tags)
+                .into_iter()
+                .map(|(tag, items)| (tag.map(|tag| tag.to_owned()), items))
+                .collect(),
+            Grouping::Section => {
+                let mut sections = BTreeMap::<Option<&str>, Vec<&Item>>::new();
+                for item in items {
+                    sections.entry(item.section()).or_default().push(item);
+                }
+                let unknown = sections.remove(&None);
+                sections
+                    .into_iter()
+                    .map(|(section, items)| (section.map(|section| section.to_owned()), items))
+                    .chain(unknown.map(|items| (None, items)))
+                    .collect()
+            }
+            Grouping::None => vec![(None, items.iter().collect())],
+        }
+    }
+
+    /// Render `items` under `header`, returning the plain text and the HTML.
+    pub fn render(
+        &self,
+        lang: Lang,
+        header: &str,
+        items: Vec<Item>,
+        tags: &[Tag],
+    ) -> (String, String) {
+        let page = self.arrange(items);
+        let groups = self.groups(&page.items, tags);
+        let lists = match groups.as_slice() {
+            // A single group without a name needs no heading.
+            [(None, items)] => list_html(items),
+            _ => groups
+                .iter()
+                .map(|(name, items)| {
+                    let title = match (self.group, name) {
+                        (Grouping::Section, Some(section)) => {
+                            t!(lang, "view.section", section = escape_html(section))
+                        }
+                        (Grouping::Section, None) => t!(lang, "view.no_section"),
+                        (_, Some(tag)) => t!(lang, "tag.group", tag = tag),
+                        (_, None) => t!(lang, "tag.untagged"),
+                    };
+                    format!("<em>{title}</em>{}", list_html(items))
+                })
+                .collect(),
+        };
+        let mut html = format!("<strong>{}</strong><br/>{lists}", escape_html(header));
+        if page.pages > 1 {
+            html.push_str(&escape_html(&t!(
+                lang,
+                "view.page",
+                page = page.page,
+                pages = page.pages,
+                packages = tn!(lang, "count.package", page.total)
+            )));
+            if page.page < page.pages {
+                html.push_str(&format!(" {}", t!(lang, "view.next", page = page.page + 1)));
+            }
+        }
+        (plain_text(&html), html)
+    }
+}
+
+/// A package row of a list, along with what it can be sorted and grouped by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub name: String,
+    pub path: Option<String>,
+    /// The size of the pending update, if there is one.
+    pub bump: Option<BumpKind>,
+    /// Seconds the pending update has been waiting for.
+    pub age: Option<u64>,
+    /// The content of the row.
+    pub html: String,
+}
+
+impl Item {
+    /// The ABBS section of the package, the first component of its path.
+    pub fn section(&self) -> Option<&str> {
+        self.path
+            .as_deref()
+            .and_then(|path| path.split_once('/'))
+            .map(|(section, _)| section)
+    }
+
+    pub fn update(package: &Package, history: Option<&History>) -> Self {
+        Self {
+            name: package.name.clone(),
+            path: Some(package.path.clone()),
+            bump: Some(bump(package)),
+            age: history.and_then(|history| history.pending_for(&package.name, &package.after)),
+            html: format!(
+                "<code>{}: {} -> {}</code>{}",
+                package.name,
+                package.before,
+                package.after,
+                if package.warnings.len() > 1 {
+                    format!(" ({})", &package.warnings[1])
+                } else {
+                    String::new()
+                }
+            ),
+        }
+    }
+
+    /// A subscription, sorted by the pending update of its package if the feed has one.
+    pub fn subscription(sub: &Subscription, data: &Anicca, history: &History) -> Self {
+        let package = data.packages().iter().find(|pkg| pkg.name == sub.package);
+        Self {
+            name: sub.package.clone(),
+            path: package
+                .map(|pkg| pkg.path.clone())
+                .or_else(|| history.get(&sub.package).map(|entry| entry.path.clone())),
+            bump: package.map(bump),
+            age: package.and_then(|pkg| history.pending_for(&pkg.name, &pkg.after)),
+            html: escape_html(&sub.to_string()),
+        }
+    }
+}
+
+fn bump(package: &Package) -> BumpKind {
+    Version::parse(&package.before).bump_to(&Version::parse(&package.after))
+}
+
+struct Page {
+    items: Vec<Item>,
+    /// The page shown, starting from 1.
+    page: usize,
+    pages: usize,
+    total: usize,
+}
+
+fn list_html(items: &[&Item]) -> String {
+    format!(
+        "<ul>{}</ul>",
+        items
+            .iter()
+            .map(|item| format!("<li>{}</li>", item.html))
+            .collect::<String>()
+    )
+}
+
+fn plain_text(html: &str) -> String {
+    html.replace("<code>", "")
+        .replace("</code>", "")
+        .replace("<br/>", "\n")
+        .replace("<ul>", "")
+        .replace("</ul>", "")
+        .replace("<li>", "- ")
+        .replace("</li>", "\n")
+        .replace("<em>", "")
+        .replace("</em>", ":\n")
+        .replace("<strong>", "")
+        .replace("</strong>", ": ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, path: Option<&str>, bump: Option<BumpKind>, age: Option<u64>) -> Item {
+        Item {
+            name: name.to_owned(),
+            path: path.map(|path| path.to_owned()),
+            bump,
+            age,
+            html: name.to_owned(),
+        }
+    }
+
+    fn names(page: &Page) -> Vec<&str> {
+        page.items.iter().map(|item| item.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(View::parse(None, None, None, None), Ok(View::default()));
+        assert_eq!(
+            View::parse(Some("age"), Some("section"), Some("2"), Some("10")),
+            Ok(View {
+                sort: SortKey::Age,
+                group: Grouping::Section,
+                page: 2,
+                per_page: 10,
+            })
+        );
+        assert_eq!(
+            View::parse(Some("size"), None, None, None),
+            Err(ViewError::Sort("size".to_owned()))
+        );
+        assert_eq!(
+            View::parse(None, None, Some("0"), None),
+            Err(ViewError::Number("page", "0".to_owned()))
+        );
+        assert!(View::parse(None, None, None, Some("1000")).is_err());
+    }
+
+    #[test]
+    fn test_arrange() {
+        let items = vec![
+            item("c", Some("extra-a/c"), Some(BumpKind::Patch), Some(10)),
+            item("a", None, None, None),
+            item("b", Some("core-b/b"), Some(BumpKind::Major), Some(5)),
+            item("d", Some("core-b/d"), Some(BumpKind::Major), None),
+        ];
+        let view = |sort| View {
+            sort,
+            ..View::default()
+        };
+        assert_eq!(
+            names(&view(SortKey::Name).arrange(items.clone())),
+            ["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            names(&view(SortKey::Path).arrange(items.clone())),
+            ["b", "d", "c", "a"]
+        );
+        assert_eq!(
+            names(&view(SortKey::Bump).arrange(items.clone())),
+            ["b", "d", "c", "a"]
+        );
+        assert_eq!(
+            names(&view(SortKey::Age).arrange(items.clone())),
+            ["c", "b", "a", "d"]
+        );
+
+        let page = View {
+            page: 5,
+            per_page: 3,
+            ..View::default()
+        }
+        .arrange(items);
+        assert_eq!(names(&page), ["d"]);
+        assert_eq!((page.page, page.pages, page.total), (2, 2, 4));
+    }
+}
@@ -1,4 +1,4 @@
-use matrix_sdk::ruma::{OwnedUserId, UserId};
+use matrix_sdk::ruma::{OwnedRoomId, OwnedServerName, OwnedUserId, UserId};
 use serde::{Deserialize, Serialize};
 use std::{path::Path, sync::Arc};
 use tokio::fs;
@@ -7,6 +7,62 @@ use tokio::fs;
 pub struct Config {
     pub admin_ids: Arc<[OwnedUserId]>,
     pub data_dir: Option<Arc<Path>>,
+    /// A room where admin commands are accepted as well as in direct messages.
+    #[serde(default)]
+    pub admin_room: Option<OwnedRoomId>,
+    /// Send the errors of commands to the admins in direct messages.
+    #[serde(default)]
+    pub report_errors: bool,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    #[serde(default)]
+    pub access: Access,
+    #[serde(default)]
+    pub invites: InvitePolicy,
+}
+
+/// Who may invite the bot and run commands, on top of the denylist kept by the admins. Admins always may.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Access {
+    /// If this or `allow_servers` is not empty, only the users listed in either may use the bot.
+    pub allow_users: Arc<[OwnedUserId]>,
+    pub allow_servers: Arc<[OwnedServerName]>,
+    pub deny_users: Arc<[OwnedUserId]>,
+    pub deny_servers: Arc<[OwnedServerName]>,
+    /// Tell users who may not run commands so, instead of ignoring them.
+    pub reply_denied: bool,
+}
+
+/// How many commands users can send. A limit refilling 0 commands a minute is turned off. Admins are not limited.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct RateLimit {
+    /// Commands each user can send at once.
+    pub user_burst: u32,
+    /// Commands each user regains a minute.
+    pub user_per_minute: u32,
+    /// Commands all users together can send at once.
+    pub global_burst: u32,
+    /// Commands all users together regain a minute.
+    pub global_per_minute: u32,
+    /// Commands over the limit after which a user is blocked, or 0 to never block anyone.
+    pub strikes: u32,
+    /// How long a user stays blocked, which is also how long commands over the limit are remembered.
+    pub block_minutes: u64,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            user_burst: 5,
+            user_per_minute: 10,
+            global_burst: 30,
+            global_per_minute: 120,
+            strikes: 10,
+            block_minutes: 60,
+        }
+    }
 }
 
 impl Default for Config {
@@ -14,10 +70,36 @@ impl Default for Config {
         Self {
             admin_ids: Arc::new([]),
             data_dir: None,
+            admin_room: None,
+            report_errors: false,
+            rate_limit: RateLimit::default(),
+            access: Access::default(),
+            invites: InvitePolicy::default(),
         }
     }
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InviteMode {
+    /// Only join direct chats.
+    #[default]
+    Direct,
+    /// Join any room.
+    Any,
+    /// Join direct chats, and other rooms once an admin approves.
+    Approval,
+}
+
+/// Which rooms the bot joins when invited.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct InvitePolicy {
+    pub mode: InviteMode,
+    /// The most rooms to be in, direct chats included, or 0 for no limit.
+    pub max_rooms: usize,
+}
+
 impl Config {
     pub async fn load<P: AsRef<Path>>(path: P) -> Self {
         let cfg = toml::from_str(&fs::read_to_string(path.as_ref()).await.unwrap_or_default())
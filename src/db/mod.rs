@@ -1,3 +1,4 @@
+use anicca_subscribe::anicca::Package;
 use eyre::Result;
 use matrix_sdk::ruma::{OwnedUserId, UserId};
 use std::path::Path;
@@ -12,6 +13,120 @@ pub type DatabaseImpl = sqlite::SqliteDatabase;
 #[cfg(feature = "rocksdb")]
 pub type DatabaseImpl = rocksdb::RocksDbDatabase;
 
+/// A package hidden from a user until its `after` version changes or `until` passes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "rocksdb", derive(wincode::SchemaWrite, wincode::SchemaRead))]
+pub struct Snooze {
+    pub package: String,
+    pub after: String,
+    pub until: Option<u64>,
+}
+
+impl Snooze {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+
+    pub fn suppresses(&self, package: &Package, now: u64) -> bool {
+        self.package == package.name && self.after == package.after && self.is_active(now)
+    }
+}
+
+/// The last update of a package a user was sent, so the same update is not sent to them again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "rocksdb", derive(wincode::SchemaWrite, wincode::SchemaRead))]
+pub struct Delivered {
+    pub package: String,
+    pub after: String,
+}
+
+impl Delivered {
+    pub fn covers(&self, package: &Package) -> bool {
+        self.package == package.name && self.after == package.after
+    }
+}
+
+/// The raw version constraint a user attached to one of their subscriptions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "rocksdb", derive(wincode::SchemaWrite, wincode::SchemaRead))]
+pub struct PackageConstraint {
+    pub package: String,
+    pub constraint: String,
+}
+
+/// Whether a user wants to see pre-release updates of one subscription, regardless of their
+/// default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "rocksdb", derive(wincode::SchemaWrite, wincode::SchemaRead))]
+pub struct PrereleaseOverride {
+    pub package: String,
+    pub show: bool,
+}
+
+/// A named filter expression whose matches count as subscriptions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "rocksdb", derive(wincode::SchemaWrite, wincode::SchemaRead))]
+pub struct SavedFilter {
+    pub name: String,
+    pub expression: String,
+}
+
+/// A named set of packages curated by admins, which users subscribe to as a whole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    pub name: String,
+    pub packages: Vec<String>,
+}
+
+/// A notification sent to a user along with the packages it listed, so replies to it can act on them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "rocksdb", derive(wincode::SchemaWrite, wincode::SchemaRead))]
+pub struct LoggedNotification {
+    pub event_id: String,
+    pub packages: Vec<String>,
+    pub sent: u64,
+}
+
+/// An admin command, recorded for auditing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub time: u64,
+    pub admin: String,
+    pub command: String,
+}
+
+/// A label a user put on some packages to manage them as a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "rocksdb", derive(wincode::SchemaWrite, wincode::SchemaRead))]
+pub struct Tag {
+    pub name: String,
+    pub packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preference {
+    HidePrerelease,
+    Language,
+    /// Whether other users may follow the user's subscriptions.
+    PublicList,
+    /// Comma separated tags whose packages are left out of notifications.
+    MutedTags,
+    /// Whether the user wants to be told about new versions of the bot.
+    Announcements,
+}
+
+impl Preference {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::HidePrerelease => "hide_prerelease",
+            Self::Language => "language",
+            Self::PublicList => "public_list",
+            Self::MutedTags => "muted_tags",
+            Self::Announcements => "announcements",
+        }
+    }
+}
+
 pub trait Database: Clone + Sync + Send {
     fn new(data: &Path) -> Result<Self>;
     async fn init(&self) -> Result<()>;
@@ -23,6 +138,92 @@ pub trait Database: Clone + Sync + Send {
     async fn disable_notification(&self, user_id: &UserId) -> Result<()>;
     async fn notification_targets(&self) -> Result<Vec<OwnedUserId>>;
     async fn users(&self) -> Result<Vec<OwnedUserId>>;
+    async fn subscribers(&self, package: &str) -> Result<Vec<OwnedUserId>>;
+    async fn get_snoozes(&self, user_id: &UserId) -> Result<Vec<Snooze>>;
+    async fn snooze(&self, user_id: &UserId, snoozes: Vec<Snooze>) -> Result<()>;
+    async fn unsnooze(&self, user_id: &UserId, packages: Vec<String>) -> Result<()>;
+    async fn get_delivered(&self, user_id: &UserId) -> Result<Vec<Delivered>>;
+    async fn mark_delivered(&self, user_id: &UserId, delivered: Vec<Delivered>) -> Result<()>;
+    async fn clear_delivered(&self, user_id: &UserId, packages: Vec<String>) -> Result<()>;
+    async fn get_constraints(&self, user_id: &UserId) -> Result<Vec<PackageConstraint>>;
+    async fn set_constraints(
+        &self,
+        user_id: &UserId,
+        constraints: Vec<PackageConstraint>,
+    ) -> Result<()>;
+    async fn clear_constraints(&self, user_id: &UserId, packages: Vec<String>) -> Result<()>;
+    async fn get_preference(
+        &self,
+        user_id: &UserId,
+        preference: Preference,
+    ) -> Result<Option<String>>;
+    async fn set_preference(
+        &self,
+        user_id: &UserId,
+        preference: Preference,
+        value: Option<String>,
+    ) -> Result<()>;
+    /// Users who set `preference` to `value`.
+    async fn users_with_preference(
+        &self,
+        preference: Preference,
+        value: &str,
+    ) -> Result<Vec<OwnedUserId>>;
+    async fn get_prerelease_overrides(&self, user_id: &UserId) -> Result<Vec<PrereleaseOverride>>;
+    async fn set_prerelease_overrides(
+        &self,
+        user_id: &UserId,
+        overrides: Vec<PrereleaseOverride>,
+    ) -> Result<()>;
+    async fn clear_prerelease_overrides(
+        &self,
+        user_id: &UserId,
+        packages: Vec<String>,
+    ) -> Result<()>;
+    async fn get_filters(&self, user_id: &UserId) -> Result<Vec<SavedFilter>>;
+    async fn add_filter(&self, user_id: &UserId, filter: SavedFilter) -> Result<()>;
+    async fn remove_filters(&self, user_id: &UserId, names: Vec<String>) -> Result<()>;
+    async fn get_collections(&self) -> Result<Vec<Collection>>;
+    async fn add_to_collection(&self, name: &str, packages: Vec<String>) -> Result<()>;
+    async fn remove_from_collection(&self, name: &str, packages: Vec<String>) -> Result<()>;
+    /// Delete a collection along with every subscription to it.
+    async fn delete_collection(&self, name: &str) -> Result<()>;
+    async fn get_collection_subscriptions(&self, user_id: &UserId) -> Result<Vec<String>>;
+    async fn subscribe_collections(&self, user_id: &UserId, names: Vec<String>) -> Result<()>;
+    async fn unsubscribe_collections(&self, user_id: &UserId, names: Vec<String>) -> Result<()>;
+    async fn collection_subscribers(&self, name: &str) -> Result<Vec<OwnedUserId>>;
+    async fn get_follows(&self, user_id: &UserId) -> Result<Vec<OwnedUserId>>;
+    async fn follow(&self, user_id: &UserId, followed: &UserId) -> Result<()>;
+    async fn unfollow(&self, user_id: &UserId, followed: Vec<OwnedUserId>) -> Result<()>;
+    async fn get_tags(&self, user_id: &UserId) -> Result<Vec<Tag>>;
+    async fn add_to_tag(&self, user_id: &UserId, name: &str, packages: Vec<String>) -> Result<()>;
+    async fn remove_from_tag(
+        &self,
+        user_id: &UserId,
+        name: &str,
+        packages: Vec<String>,
+    ) -> Result<()>;
+    async fn delete_tag(&self, user_id: &UserId, name: &str) -> Result<()>;
+    async fn get_notification_log(&self, user_id: &UserId) -> Result<Vec<LoggedNotification>>;
+    /// Record a notification, forgetting the ones sent before `expire_before`.
+    async fn log_notification(
+        &self,
+        user_id: &UserId,
+        notification: LoggedNotification,
+        expire_before: u64,
+    ) -> Result<()>;
+    /// User ids and server names the admins denied access to the bot.
+    async fn get_denylist(&self) -> Result<Vec<String>>;
+    async fn deny(&self, entries: Vec<String>) -> Result<()>;
+    async fn undeny(&self, entries: Vec<String>) -> Result<()>;
+    /// The last bot version announced to the users.
+    async fn get_announced_version(&self) -> Result<Option<String>>;
+    async fn set_announced_version(&self, version: &str) -> Result<()>;
+    /// Delete everything stored about a user, and stop others from following them.
+    async fn purge_user(&self, user_id: &UserId) -> Result<()>;
+    async fn log_admin_action(&self, entry: AuditEntry) -> Result<()>;
+    /// The latest `limit` admin actions, oldest first.
+    async fn get_audit_log(&self, limit: usize) -> Result<Vec<AuditEntry>>;
 }
 
 #[cfg(test)]
@@ -48,6 +249,8 @@ mod tests {
         assert_eq!(DB.users().await?, vec![USER.clone()]);
         DB.unsubscribe(&USER, vec!["xyz".to_owned()]).await?;
         assert_eq!(DB.get_packages(&USER).await?, vec!["abc".to_owned()]);
+        assert_eq!(DB.subscribers("abc").await?, vec![USER.clone()]);
+        assert_eq!(DB.subscribers("xyz").await?, Vec::<OwnedUserId>::new());
         DB.unsubscribe(&USER, vec!["abc".to_owned()]).await?;
         assert_eq!(DB.get_packages(&USER).await?, Vec::<String>::new());
         assert_eq!(DB.users().await?, Vec::<OwnedUserId>::new());
@@ -69,4 +272,388 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_snooze() -> Result<()> {
+        DB.init().await?;
+        let snooze = Snooze {
+            package: "abc".to_owned(),
+            after: "1.0".to_owned(),
+            until: None,
+        };
+        DB.snooze(&USER, vec![snooze.clone()]).await?;
+        assert_eq!(DB.get_snoozes(&USER).await?, vec![snooze.clone()]);
+        let snooze = Snooze {
+            until: Some(42),
+            ..snooze
+        };
+        DB.snooze(&USER, vec![snooze.clone()]).await?;
+        assert_eq!(DB.get_snoozes(&USER).await?, vec![snooze]);
+        assert_eq!(DB.users().await?, Vec::<OwnedUserId>::new());
+        DB.unsnooze(&USER, vec!["abc".to_owned()]).await?;
+        assert_eq!(DB.get_snoozes(&USER).await?, Vec::<Snooze>::new());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delivered() -> Result<()> {
+        DB.init().await?;
+        let delivered = Delivered {
+            package: "abc".to_owned(),
+            after: "1.0".to_owned(),
+        };
+        DB.mark_delivered(&USER, vec![delivered.clone()]).await?;
+        assert_eq!(DB.get_delivered(&USER).await?, vec![delivered.clone()]);
+        let delivered = Delivered {
+            after: "1.1".to_owned(),
+            ..delivered
+        };
+        DB.mark_delivered(&USER, vec![delivered.clone()]).await?;
+        assert_eq!(DB.get_delivered(&USER).await?, vec![delivered]);
+        DB.clear_delivered(&USER, vec!["abc".to_owned()]).await?;
+        assert_eq!(DB.get_delivered(&USER).await?, Vec::<Delivered>::new());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_constraint() -> Result<()> {
+        DB.init().await?;
+        let constraint = PackageConstraint {
+            package: "abc".to_owned(),
+            constraint: ">=20".to_owned(),
+        };
+        DB.set_constraints(&USER, vec![constraint.clone()]).await?;
+        assert_eq!(DB.get_constraints(&USER).await?, vec![constraint.clone()]);
+        let constraint = PackageConstraint {
+            constraint: "@major".to_owned(),
+            ..constraint
+        };
+        DB.set_constraints(&USER, vec![constraint.clone()]).await?;
+        assert_eq!(DB.get_constraints(&USER).await?, vec![constraint]);
+        DB.clear_constraints(&USER, vec!["abc".to_owned()]).await?;
+        assert_eq!(
+            DB.get_constraints(&USER).await?,
+            Vec::<PackageConstraint>::new()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_preference() -> Result<()> {
+        DB.init().await?;
+        assert_eq!(
+            DB.get_preference(&USER, Preference::HidePrerelease).await?,
+            None
+        );
+        DB.set_preference(&USER, Preference::HidePrerelease, Some("true".to_owned()))
+            .await?;
+        assert_eq!(
+            DB.get_preference(&USER, Preference::HidePrerelease).await?,
+            Some("true".to_owned())
+        );
+        DB.set_preference(&USER, Preference::HidePrerelease, Some("false".to_owned()))
+            .await?;
+        assert_eq!(
+            DB.get_preference(&USER, Preference::HidePrerelease).await?,
+            Some("false".to_owned())
+        );
+        assert_eq!(
+            DB.users_with_preference(Preference::HidePrerelease, "false")
+                .await?,
+            vec![USER.clone()]
+        );
+        assert_eq!(
+            DB.users_with_preference(Preference::HidePrerelease, "true")
+                .await?,
+            Vec::<OwnedUserId>::new()
+        );
+        DB.set_preference(&USER, Preference::HidePrerelease, None)
+            .await?;
+        assert_eq!(
+            DB.get_preference(&USER, Preference::HidePrerelease).await?,
+            None
+        );
+        assert_eq!(
+            DB.users_with_preference(Preference::HidePrerelease, "false")
+                .await?,
+            Vec::<OwnedUserId>::new()
+        );
+        assert_eq!(DB.users().await?, Vec::<OwnedUserId>::new());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_prerelease_override() -> Result<()> {
+        DB.init().await?;
+        let show = PrereleaseOverride {
+            package: "abc".to_owned(),
+            show: true,
+        };
+        DB.set_prerelease_overrides(&USER, vec![show.clone()])
+            .await?;
+        assert_eq!(DB.get_prerelease_overrides(&USER).await?, vec![show]);
+        let hide = PrereleaseOverride {
+            package: "abc".to_owned(),
+            show: false,
+        };
+        DB.set_prerelease_overrides(&USER, vec![hide.clone()])
+            .await?;
+        assert_eq!(DB.get_prerelease_overrides(&USER).await?, vec![hide]);
+        DB.clear_prerelease_overrides(&USER, vec!["abc".to_owned()])
+            .await?;
+        assert_eq!(
+            DB.get_prerelease_overrides(&USER).await?,
+            Vec::<PrereleaseOverride>::new()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_filter() -> Result<()> {
+        DB.init().await?;
+        let filter = SavedFilter {
+            name: "lang".to_owned(),
+            expression: "path ^= lang-".to_owned(),
+        };
+        DB.add_filter(&USER, filter.clone()).await?;
+        assert_eq!(DB.get_filters(&USER).await?, vec![filter.clone()]);
+        let filter = SavedFilter {
+            expression: "path ^= lang- and bump >= minor".to_owned(),
+            ..filter
+        };
+        DB.add_filter(&USER, filter.clone()).await?;
+        assert_eq!(DB.get_filters(&USER).await?, vec![filter]);
+        DB.remove_filters(&USER, vec!["lang".to_owned()]).await?;
+        assert_eq!(DB.get_filters(&USER).await?, Vec::<SavedFilter>::new());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_collection() -> Result<()> {
+        DB.init().await?;
+        DB.add_to_collection("toolchain", vec!["llvm".to_owned(), "gcc".to_owned()])
+            .await?;
+        DB.add_to_collection("toolchain", vec!["gcc".to_owned(), "rustc".to_owned()])
+            .await?;
+        DB.add_to_collection("kde", vec!["kcoreaddons".to_owned()])
+            .await?;
+        DB.remove_from_collection("toolchain", vec!["llvm".to_owned()])
+            .await?;
+        assert_eq!(
+            DB.get_collections().await?,
+            vec![
+                Collection {
+                    name: "kde".to_owned(),
+                    packages: vec!["kcoreaddons".to_owned()],
+                },
+                Collection {
+                    name: "toolchain".to_owned(),
+                    packages: vec!["gcc".to_owned(), "rustc".to_owned()],
+                },
+            ]
+        );
+        DB.subscribe_collections(&USER, vec!["toolchain".to_owned(), "kde".to_owned()])
+            .await?;
+        DB.unsubscribe_collections(&USER, vec!["kde".to_owned()])
+            .await?;
+        assert_eq!(
+            DB.get_collection_subscriptions(&USER).await?,
+            vec!["toolchain".to_owned()]
+        );
+        assert_eq!(
+            DB.collection_subscribers("toolchain").await?,
+            vec![USER.clone()]
+        );
+        assert_eq!(
+            DB.collection_subscribers("kde").await?,
+            Vec::<OwnedUserId>::new()
+        );
+        DB.delete_collection("toolchain").await?;
+        DB.delete_collection("kde").await?;
+        assert_eq!(DB.get_collections().await?, Vec::<Collection>::new());
+        assert_eq!(
+            DB.get_collection_subscriptions(&USER).await?,
+            Vec::<String>::new()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_follow() -> Result<()> {
+        DB.init().await?;
+        let other = UserId::parse("@xyz:example.com")?;
+        DB.follow(&USER, &other).await?;
+        DB.follow(&USER, &other).await?;
+        assert_eq!(DB.get_follows(&USER).await?, vec![other.clone()]);
+        DB.unfollow(&USER, vec![other]).await?;
+        assert_eq!(DB.get_follows(&USER).await?, Vec::<OwnedUserId>::new());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_tag() -> Result<()> {
+        DB.init().await?;
+        DB.add_to_tag(&USER, "gui", vec!["qt".to_owned(), "gtk".to_owned()])
+            .await?;
+        DB.add_to_tag(&USER, "gui", vec!["gtk".to_owned()]).await?;
+        DB.add_to_tag(&USER, "cli", vec!["curl".to_owned()]).await?;
+        DB.remove_from_tag(&USER, "gui", vec!["qt".to_owned()])
+            .await?;
+        assert_eq!(
+            DB.get_tags(&USER).await?,
+            vec![
+                Tag {
+                    name: "cli".to_owned(),
+                    packages: vec!["curl".to_owned()],
+                },
+                Tag {
+                    name: "gui".to_owned(),
+                    packages: vec!["gtk".to_owned()],
+                },
+            ]
+        );
+        DB.delete_tag(&USER, "cli").await?;
+        DB.remove_from_tag(&USER, "gui", vec!["gtk".to_owned()])
+            .await?;
+        assert_eq!(DB.get_tags(&USER).await?, Vec::<Tag>::new());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_notification_log() -> Result<()> {
+        DB.init().await?;
+        let old = LoggedNotification {
+            event_id: "$old".to_owned(),
+            packages: vec!["abc".to_owned()],
+            sent: 100,
+        };
+        let new = LoggedNotification {
+            event_id: "$new".to_owned(),
+            packages: vec!["abc".to_owned(), "xyz".to_owned()],
+            sent: 200,
+        };
+        DB.log_notification(&USER, old.clone(), 0).await?;
+        DB.log_notification(&USER, new.clone(), 0).await?;
+        assert_eq!(
+            DB.get_notification_log(&USER).await?,
+            vec![old, new.clone()]
+        );
+        DB.log_notification(
+            &USER,
+            LoggedNotification {
+                event_id: "$newer".to_owned(),
+                packages: Vec::new(),
+                sent: 300,
+            },
+            150,
+        )
+        .await?;
+        assert_eq!(DB.get_notification_log(&USER).await?, vec![new]);
+        DB.log_notification(
+            &USER,
+            LoggedNotification {
+                event_id: "$newest".to_owned(),
+                packages: Vec::new(),
+                sent: 400,
+            },
+            1000,
+        )
+        .await?;
+        assert_eq!(DB.get_notification_log(&USER).await?, Vec::new());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_denylist() -> Result<()> {
+        DB.init().await?;
+        DB.deny(vec![
+            "@spam:example.org".to_owned(),
+            "evil.example".to_owned(),
+        ])
+        .await?;
+        DB.deny(vec!["evil.example".to_owned()]).await?;
+        let mut denylist = DB.get_denylist().await?;
+        denylist.sort();
+        assert_eq!(denylist, ["@spam:example.org", "evil.example"]);
+        DB.undeny(vec!["@spam:example.org".to_owned()]).await?;
+        assert_eq!(DB.get_denylist().await?, ["evil.example"]);
+        DB.undeny(vec!["evil.example".to_owned()]).await?;
+        assert_eq!(DB.get_denylist().await?, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_announced_version() -> Result<()> {
+        DB.init().await?;
+        DB.set_announced_version("0.4.1").await?;
+        DB.set_announced_version("0.4.2").await?;
+        assert_eq!(DB.get_announced_version().await?, Some("0.4.2".to_owned()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_purge_user() -> Result<()> {
+        DB.init().await?;
+        let follower = UserId::parse("@follower:example.com").unwrap();
+        DB.subscribe(&USER, PACKAGES.clone()).await?;
+        DB.enable_notification(&USER).await?;
+        DB.set_preference(&USER, Preference::Language, Some("en".to_owned()))
+            .await?;
+        DB.add_to_tag(&USER, "work", PACKAGES.clone()).await?;
+        DB.subscribe(&follower, PACKAGES.clone()).await?;
+        DB.follow(&follower, &USER).await?;
+        DB.purge_user(&USER).await?;
+        assert_eq!(DB.get_packages(&USER).await?, Vec::<String>::new());
+        assert!(!DB.is_notification_enabled(&USER).await?);
+        assert_eq!(DB.get_preference(&USER, Preference::Language).await?, None);
+        assert_eq!(DB.get_tags(&USER).await?, Vec::<Tag>::new());
+        assert!(!DB.users().await?.contains(&USER));
+        assert_eq!(DB.get_follows(&follower).await?, Vec::<OwnedUserId>::new());
+        DB.purge_user(&follower).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_audit_log() -> Result<()> {
+        DB.init().await?;
+        let entry = |time: u64, command: &str| AuditEntry {
+            time,
+            admin: USER.to_string(),
+            command: command.to_owned(),
+        };
+        DB.log_admin_action(entry(100, "+users")).await?;
+        DB.log_admin_action(entry(200, "+stats")).await?;
+        DB.log_admin_action(entry(200, "+list @abc:example.com"))
+            .await?;
+        assert_eq!(
+            DB.get_audit_log(2).await?,
+            [entry(200, "+stats"), entry(200, "+list @abc:example.com")]
+        );
+        assert_eq!(DB.get_audit_log(10).await?.len(), 3);
+        Ok(())
+    }
 }
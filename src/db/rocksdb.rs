@@ -5,6 +5,45 @@ use std::{path::Path, sync::Arc};
 use tokio::task::spawn_blocking;
 use wincode::{SchemaRead, SchemaWrite, config};
 
+use super::{
+    AuditEntry, Collection, Delivered, LoggedNotification, PackageConstraint, Preference,
+    PrereleaseOverride, SavedFilter, Snooze, Tag,
+};
+
+const CF_SNOOZE: &str = "snooze";
+const CF_CONSTRAINT: &str = "constraint";
+const CF_PREFERENCE: &str = "preference";
+const CF_PRERELEASE: &str = "prerelease";
+const CF_FILTER: &str = "filter";
+const CF_DELIVERED: &str = "delivered";
+/// Packages keyed by collection name rather than by user.
+const CF_COLLECTION: &str = "collection";
+const CF_COLLECTION_SUBSCRIPTION: &str = "collection_subscription";
+const CF_FOLLOW: &str = "follow";
+const CF_TAG: &str = "tag";
+const CF_NOTIFICATION_LOG: &str = "notification_log";
+/// Settings of the bot itself rather than of a user, keyed by name.
+const CF_BOT: &str = "bot";
+/// Admin commands keyed by their zero-padded time and the admin who sent them.
+const CF_AUDIT_LOG: &str = "audit_log";
+const DENYLIST_KEY: &str = "denylist";
+const ANNOUNCED_VERSION_KEY: &str = "announced_version";
+const COLUMN_FAMILIES: [&str; 13] = [
+    CF_SNOOZE,
+    CF_CONSTRAINT,
+    CF_PREFERENCE,
+    CF_PRERELEASE,
+    CF_FILTER,
+    CF_DELIVERED,
+    CF_COLLECTION,
+    CF_COLLECTION_SUBSCRIPTION,
+    CF_FOLLOW,
+    CF_TAG,
+    CF_NOTIFICATION_LOG,
+    CF_BOT,
+    CF_AUDIT_LOG,
+];
+
 type WincodeConfig = config::Configuration<
     true,
     { usize::MAX },
@@ -26,6 +65,12 @@ pub struct User {
     notification_enabled: bool,
 }
 
+#[derive(SchemaWrite, SchemaRead, Debug, Clone)]
+pub struct PreferenceValue {
+    key: String,
+    value: String,
+}
+
 impl RocksDbDatabase {
     async fn get_user_data(&self, user_id: &UserId) -> Result<Option<User>> {
         let user_id_str = user_id.to_string();
@@ -47,16 +92,142 @@ impl RocksDbDatabase {
             .await?
             .unwrap_or(User::default()))
     }
+
+    async fn get_cf(&self, cf_name: &'static str, key: &str) -> Result<Option<Vec<u8>>> {
+        let key = key.to_owned();
+        let db = self.db.clone();
+        Ok(spawn_blocking(move || {
+            let cf = db.cf_handle(cf_name).unwrap();
+            db.get_cf(&cf, key.as_bytes())
+        })
+        .await??)
+    }
+
+    /// Store `value` under `key`, or delete the key if there is nothing to store.
+    async fn put_cf(&self, cf_name: &'static str, key: &str, value: Option<Vec<u8>>) -> Result<()> {
+        let key = key.to_owned();
+        let db = self.db.clone();
+        spawn_blocking(move || {
+            let cf = db.cf_handle(cf_name).unwrap();
+            match value {
+                Some(value) => db.put_cf(&cf, key.as_bytes(), value),
+                None => db.delete_cf(&cf, key.as_bytes()),
+            }
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn put_snoozes(&self, user_id: &UserId, snoozes: Vec<Snooze>) -> Result<()> {
+        let value = if snoozes.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&snoozes, self.wincode_config)?)
+        };
+        self.put_cf(CF_SNOOZE, user_id.as_str(), value).await
+    }
+
+    async fn put_constraints(
+        &self,
+        user_id: &UserId,
+        constraints: Vec<PackageConstraint>,
+    ) -> Result<()> {
+        let value = if constraints.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&constraints, self.wincode_config)?)
+        };
+        self.put_cf(CF_CONSTRAINT, user_id.as_str(), value).await
+    }
+
+    async fn get_preferences(&self, user_id: &UserId) -> Result<Vec<PreferenceValue>> {
+        if let Some(data) = self.get_cf(CF_PREFERENCE, user_id.as_str()).await? {
+            Ok(config::deserialize::<Vec<PreferenceValue>, WincodeConfig>(
+                &data,
+                self.wincode_config,
+            )?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn put_prerelease_overrides(
+        &self,
+        user_id: &UserId,
+        overrides: Vec<PrereleaseOverride>,
+    ) -> Result<()> {
+        let value = if overrides.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&overrides, self.wincode_config)?)
+        };
+        self.put_cf(CF_PRERELEASE, user_id.as_str(), value).await
+    }
+
+    async fn put_filters(&self, user_id: &UserId, filters: Vec<SavedFilter>) -> Result<()> {
+        let value = if filters.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&filters, self.wincode_config)?)
+        };
+        self.put_cf(CF_FILTER, user_id.as_str(), value).await
+    }
+
+    async fn put_delivered(&self, user_id: &UserId, delivered: Vec<Delivered>) -> Result<()> {
+        let value = if delivered.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&delivered, self.wincode_config)?)
+        };
+        self.put_cf(CF_DELIVERED, user_id.as_str(), value).await
+    }
+
+    async fn put_tags(&self, user_id: &UserId, mut tags: Vec<Tag>) -> Result<()> {
+        tags.retain(|tag| !tag.packages.is_empty());
+        let value = if tags.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&tags, self.wincode_config)?)
+        };
+        self.put_cf(CF_TAG, user_id.as_str(), value).await
+    }
+
+    async fn get_strings(&self, cf_name: &'static str, key: &str) -> Result<Vec<String>> {
+        if let Some(data) = self.get_cf(cf_name, key).await? {
+            Ok(config::deserialize::<Vec<String>, WincodeConfig>(
+                &data,
+                self.wincode_config,
+            )?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn put_strings(
+        &self,
+        cf_name: &'static str,
+        key: &str,
+        values: Vec<String>,
+    ) -> Result<()> {
+        let value = if values.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&values, self.wincode_config)?)
+        };
+        self.put_cf(cf_name, key, value).await
+    }
 }
 
 impl super::Database for RocksDbDatabase {
     fn new(data_dir: &Path) -> Result<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
 
-        let db = Arc::new(DBWithThreadMode::<MultiThreaded>::open(
+        let db = Arc::new(DBWithThreadMode::<MultiThreaded>::open_cf(
             &opts,
             data_dir.join("anicca"),
+            COLUMN_FAMILIES,
         )?);
         let wincode_config = config::Configuration::default()
             .disable_preallocation_size_limit()
@@ -171,4 +342,541 @@ impl super::Database for RocksDbDatabase {
         })
         .await??)
     }
+
+    async fn subscribers(&self, package: &str) -> Result<Vec<OwnedUserId>> {
+        let db = self.db.clone();
+        let wincode_config = self.wincode_config;
+        let package = package.to_owned();
+        spawn_blocking(move || {
+            let mut users = Vec::new();
+            let iter = db.iterator(rocksdb::IteratorMode::Start);
+            for item in iter {
+                let (key, value) = item?;
+                let user = config::deserialize::<User, WincodeConfig>(&value, wincode_config)?;
+                if user.packages.contains(&package)
+                    && let Ok(user_id) = str::from_utf8(&key)
+                {
+                    users.push(UserId::parse(user_id)?);
+                }
+            }
+            Ok(users)
+        })
+        .await?
+    }
+
+    async fn get_snoozes(&self, user_id: &UserId) -> Result<Vec<Snooze>> {
+        if let Some(data) = self.get_cf(CF_SNOOZE, user_id.as_str()).await? {
+            Ok(config::deserialize::<Vec<Snooze>, WincodeConfig>(
+                &data,
+                self.wincode_config,
+            )?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn snooze(&self, user_id: &UserId, snoozes: Vec<Snooze>) -> Result<()> {
+        let mut current = self.get_snoozes(user_id).await?;
+        current.retain(|old| !snoozes.iter().any(|new| new.package == old.package));
+        current.extend(snoozes);
+        self.put_snoozes(user_id, current).await
+    }
+
+    async fn unsnooze(&self, user_id: &UserId, packages: Vec<String>) -> Result<()> {
+        let mut current = self.get_snoozes(user_id).await?;
+        current.retain(|snooze| !packages.contains(&snooze.package));
+        self.put_snoozes(user_id, current).await
+    }
+
+    async fn get_delivered(&self, user_id: &UserId) -> Result<Vec<Delivered>> {
+        if let Some(data) = self.get_cf(CF_DELIVERED, user_id.as_str()).await? {
+            Ok(config::deserialize::<Vec<Delivered>, WincodeConfig>(
+                &data,
+                self.wincode_config,
+            )?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn mark_delivered(&self, user_id: &UserId, delivered: Vec<Delivered>) -> Result<()> {
+        let mut current = self.get_delivered(user_id).await?;
+        current.retain(|old| !delivered.iter().any(|new| new.package == old.package));
+        current.extend(delivered);
+        self.put_delivered(user_id, current).await
+    }
+
+    async fn clear_delivered(&self, user_id: &UserId, packages: Vec<String>) -> Result<()> {
+        let mut current = self.get_delivered(user_id).await?;
+        current.retain(|item| !packages.contains(&item.package));
+        self.put_delivered(user_id, current).await
+    }
+
+    async fn get_constraints(&self, user_id: &UserId) -> Result<Vec<PackageConstraint>> {
+        if let Some(data) = self.get_cf(CF_CONSTRAINT, user_id.as_str()).await? {
+            Ok(
+                config::deserialize::<Vec<PackageConstraint>, WincodeConfig>(
+                    &data,
+                    self.wincode_config,
+                )?,
+            )
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn set_constraints(
+        &self,
+        user_id: &UserId,
+        constraints: Vec<PackageConstraint>,
+    ) -> Result<()> {
+        let mut current = self.get_constraints(user_id).await?;
+        current.retain(|old| !constraints.iter().any(|new| new.package == old.package));
+        current.extend(constraints);
+        self.put_constraints(user_id, current).await
+    }
+
+    async fn clear_constraints(&self, user_id: &UserId, packages: Vec<String>) -> Result<()> {
+        let mut current = self.get_constraints(user_id).await?;
+        current.retain(|constraint| !packages.contains(&constraint.package));
+        self.put_constraints(user_id, current).await
+    }
+
+    async fn get_preference(
+        &self,
+        user_id: &UserId,
+        preference: Preference,
+    ) -> Result<Option<String>> {
+        Ok(self
+            .get_preferences(user_id)
+            .await?
+            .into_iter()
+            .find(|item| item.key == preference.key())
+            .map(|item| item.value))
+    }
+
+    async fn set_preference(
+        &self,
+        user_id: &UserId,
+        preference: Preference,
+        value: Option<String>,
+    ) -> Result<()> {
+        let mut preferences = self.get_preferences(user_id).await?;
+        preferences.retain(|item| item.key != preference.key());
+        if let Some(value) = value {
+            preferences.push(PreferenceValue {
+                key: preference.key().to_owned(),
+                value,
+            });
+        }
+        let value = if preferences.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&preferences, self.wincode_config)?)
+        };
+        self.put_cf(CF_PREFERENCE, user_id.as_str(), value).await
+    }
+
+    async fn users_with_preference(
+        &self,
+        preference: Preference,
+        value: &str,
+    ) -> Result<Vec<OwnedUserId>> {
+        let db = self.db.clone();
+        let wincode_config = self.wincode_config;
+        let value = value.to_owned();
+        spawn_blocking(move || {
+            let cf = db.cf_handle(CF_PREFERENCE).unwrap();
+            let mut users = Vec::new();
+            for item in db.iterator_cf(&cf, rocksdb::IteratorMode::Start) {
+                let (key, data) = item?;
+                let preferences = config::deserialize::<Vec<PreferenceValue>, WincodeConfig>(
+                    &data,
+                    wincode_config,
+                )?;
+                if preferences
+                    .iter()
+                    .any(|item| item.key == preference.key() && item.value == value)
+                {
+                    users.push(UserId::parse(str::from_utf8(&key)?)?);
+                }
+            }
+            Ok(users)
+        })
+        .await?
+    }
+
+    async fn get_prerelease_overrides(&self, user_id: &UserId) -> Result<Vec<PrereleaseOverride>> {
+        if let Some(data) = self.get_cf(CF_PRERELEASE, user_id.as_str()).await? {
+            Ok(
+                config::deserialize::<Vec<PrereleaseOverride>, WincodeConfig>(
+                    &data,
+                    self.wincode_config,
+                )?,
+            )
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn set_prerelease_overrides(
+        &self,
+        user_id: &UserId,
+        overrides: Vec<PrereleaseOverride>,
+    ) -> Result<()> {
+        let mut current = self.get_prerelease_overrides(user_id).await?;
+        current.retain(|old| !overrides.iter().any(|new| new.package == old.package));
+        current.extend(overrides);
+        self.put_prerelease_overrides(user_id, current).await
+    }
+
+    async fn clear_prerelease_overrides(
+        &self,
+        user_id: &UserId,
+        packages: Vec<String>,
+    ) -> Result<()> {
+        let mut current = self.get_prerelease_overrides(user_id).await?;
+        current.retain(|item| !packages.contains(&item.package));
+        self.put_prerelease_overrides(user_id, current).await
+    }
+
+    async fn get_filters(&self, user_id: &UserId) -> Result<Vec<SavedFilter>> {
+        if let Some(data) = self.get_cf(CF_FILTER, user_id.as_str()).await? {
+            Ok(config::deserialize::<Vec<SavedFilter>, WincodeConfig>(
+                &data,
+                self.wincode_config,
+            )?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn add_filter(&self, user_id: &UserId, filter: SavedFilter) -> Result<()> {
+        let mut filters = self.get_filters(user_id).await?;
+        filters.retain(|old| old.name != filter.name);
+        filters.push(filter);
+        self.put_filters(user_id, filters).await
+    }
+
+    async fn remove_filters(&self, user_id: &UserId, names: Vec<String>) -> Result<()> {
+        let mut filters = self.get_filters(user_id).await?;
+        filters.retain(|filter| !names.contains(&filter.name));
+        self.put_filters(user_id, filters).await
+    }
+
+    async fn get_collections(&self) -> Result<Vec<Collection>> {
+        let db = self.db.clone();
+        let wincode_config = self.wincode_config;
+        spawn_blocking(move || {
+            let cf = db.cf_handle(CF_COLLECTION).unwrap();
+            let mut collections = Vec::new();
+            for item in db.iterator_cf(&cf, rocksdb::IteratorMode::Start) {
+                let (key, value) = item?;
+                let mut packages =
+                    config::deserialize::<Vec<String>, WincodeConfig>(&value, wincode_config)?;
+                packages.sort();
+                collections.push(Collection {
+                    name: String::from_utf8(key.to_vec())?,
+                    packages,
+                });
+            }
+            Ok(collections)
+        })
+        .await?
+    }
+
+    async fn add_to_collection(&self, name: &str, packages: Vec<String>) -> Result<()> {
+        let mut current = self.get_strings(CF_COLLECTION, name).await?;
+        for package in packages {
+            if !current.contains(&package) {
+                current.push(package);
+            }
+        }
+        self.put_strings(CF_COLLECTION, name, current).await
+    }
+
+    async fn remove_from_collection(&self, name: &str, packages: Vec<String>) -> Result<()> {
+        let mut current = self.get_strings(CF_COLLECTION, name).await?;
+        current.retain(|package| !packages.contains(package));
+        self.put_strings(CF_COLLECTION, name, current).await
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<()> {
+        self.put_cf(CF_COLLECTION, name, None).await?;
+        let db = self.db.clone();
+        let wincode_config = self.wincode_config;
+        let name = name.to_owned();
+        spawn_blocking(move || {
+            let cf = db.cf_handle(CF_COLLECTION_SUBSCRIPTION).unwrap();
+            for item in db.iterator_cf(&cf, rocksdb::IteratorMode::Start) {
+                let (key, value) = item?;
+                let mut names =
+                    config::deserialize::<Vec<String>, WincodeConfig>(&value, wincode_config)?;
+                if !names.contains(&name) {
+                    continue;
+                }
+                names.retain(|item| *item != name);
+                if names.is_empty() {
+                    db.delete_cf(&cf, key)?;
+                } else {
+                    db.put_cf(&cf, key, config::serialize(&names, wincode_config)?)?;
+                }
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn get_collection_subscriptions(&self, user_id: &UserId) -> Result<Vec<String>> {
+        self.get_strings(CF_COLLECTION_SUBSCRIPTION, user_id.as_str())
+            .await
+    }
+
+    async fn subscribe_collections(&self, user_id: &UserId, names: Vec<String>) -> Result<()> {
+        let mut current = self.get_collection_subscriptions(user_id).await?;
+        for name in names {
+            if !current.contains(&name) {
+                current.push(name);
+            }
+        }
+        self.put_strings(CF_COLLECTION_SUBSCRIPTION, user_id.as_str(), current)
+            .await
+    }
+
+    async fn unsubscribe_collections(&self, user_id: &UserId, names: Vec<String>) -> Result<()> {
+        let mut current = self.get_collection_subscriptions(user_id).await?;
+        current.retain(|name| !names.contains(name));
+        self.put_strings(CF_COLLECTION_SUBSCRIPTION, user_id.as_str(), current)
+            .await
+    }
+
+    async fn collection_subscribers(&self, name: &str) -> Result<Vec<OwnedUserId>> {
+        let db = self.db.clone();
+        let wincode_config = self.wincode_config;
+        let name = name.to_owned();
+        spawn_blocking(move || {
+            let cf = db.cf_handle(CF_COLLECTION_SUBSCRIPTION).unwrap();
+            let mut users = Vec::new();
+            for item in db.iterator_cf(&cf, rocksdb::IteratorMode::Start) {
+                let (key, value) = item?;
+                let names =
+                    config::deserialize::<Vec<String>, WincodeConfig>(&value, wincode_config)?;
+                if names.contains(&name) {
+                    users.push(UserId::parse(str::from_utf8(&key)?)?);
+                }
+            }
+            Ok(users)
+        })
+        .await?
+    }
+
+    async fn get_follows(&self, user_id: &UserId) -> Result<Vec<OwnedUserId>> {
+        Ok(self
+            .get_strings(CF_FOLLOW, user_id.as_str())
+            .await?
+            .into_iter()
+            .map(UserId::parse)
+            .collect::<Result<Vec<OwnedUserId>, _>>()?)
+    }
+
+    async fn follow(&self, user_id: &UserId, followed: &UserId) -> Result<()> {
+        let mut current = self.get_strings(CF_FOLLOW, user_id.as_str()).await?;
+        if !current.iter().any(|item| item == followed.as_str()) {
+            current.push(followed.to_string());
+        }
+        self.put_strings(CF_FOLLOW, user_id.as_str(), current).await
+    }
+
+    async fn unfollow(&self, user_id: &UserId, followed: Vec<OwnedUserId>) -> Result<()> {
+        let mut current = self.get_strings(CF_FOLLOW, user_id.as_str()).await?;
+        current.retain(|item| !followed.iter().any(|id| id.as_str() == item));
+        self.put_strings(CF_FOLLOW, user_id.as_str(), current).await
+    }
+
+    async fn get_tags(&self, user_id: &UserId) -> Result<Vec<Tag>> {
+        let mut tags = if let Some(data) = self.get_cf(CF_TAG, user_id.as_str()).await? {
+            config::deserialize::<Vec<Tag>, WincodeConfig>(&data, self.wincode_config)?
+        } else {
+            Vec::new()
+        };
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        for tag in &mut tags {
+            tag.packages.sort();
+        }
+        Ok(tags)
+    }
+
+    async fn add_to_tag(&self, user_id: &UserId, name: &str, packages: Vec<String>) -> Result<()> {
+        let mut tags = self.get_tags(user_id).await?;
+        let index = match tags.iter().position(|tag| tag.name == name) {
+            Some(index) => index,
+            None => {
+                tags.push(Tag {
+                    name: name.to_owned(),
+                    packages: Vec::new(),
+                });
+                tags.len() - 1
+            }
+        };
+        for package in packages {
+            if !tags[index].packages.contains(&package) {
+                tags[index].packages.push(package);
+            }
+        }
+        self.put_tags(user_id, tags).await
+    }
+
+    async fn remove_from_tag(
+        &self,
+        user_id: &UserId,
+        name: &str,
+        packages: Vec<String>,
+    ) -> Result<()> {
+        let mut tags = self.get_tags(user_id).await?;
+        if let Some(tag) = tags.iter_mut().find(|tag| tag.name == name) {
+            tag.packages.retain(|package| !packages.contains(package));
+        }
+        self.put_tags(user_id, tags).await
+    }
+
+    async fn delete_tag(&self, user_id: &UserId, name: &str) -> Result<()> {
+        let mut tags = self.get_tags(user_id).await?;
+        tags.retain(|tag| tag.name != name);
+        self.put_tags(user_id, tags).await
+    }
+
+    async fn get_notification_log(&self, user_id: &UserId) -> Result<Vec<LoggedNotification>> {
+        if let Some(data) = self.get_cf(CF_NOTIFICATION_LOG, user_id.as_str()).await? {
+            Ok(
+                config::deserialize::<Vec<LoggedNotification>, WincodeConfig>(
+                    &data,
+                    self.wincode_config,
+                )?,
+            )
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn log_notification(
+        &self,
+        user_id: &UserId,
+        notification: LoggedNotification,
+        expire_before: u64,
+    ) -> Result<()> {
+        let mut log = self.get_notification_log(user_id).await?;
+        log.push(notification);
+        log.retain(|notification| {
+            notification.sent >= expire_before && !notification.packages.is_empty()
+        });
+        let value = if log.is_empty() {
+            None
+        } else {
+            Some(config::serialize(&log, self.wincode_config)?)
+        };
+        self.put_cf(CF_NOTIFICATION_LOG, user_id.as_str(), value)
+            .await
+    }
+
+    async fn get_denylist(&self) -> Result<Vec<String>> {
+        self.get_strings(CF_BOT, DENYLIST_KEY).await
+    }
+
+    async fn deny(&self, entries: Vec<String>) -> Result<()> {
+        let mut current = self.get_denylist().await?;
+        for entry in entries {
+            if !current.contains(&entry) {
+                current.push(entry);
+            }
+        }
+        self.put_strings(CF_BOT, DENYLIST_KEY, current).await
+    }
+
+    async fn undeny(&self, entries: Vec<String>) -> Result<()> {
+        let mut current = self.get_denylist().await?;
+        current.retain(|entry| !entries.contains(entry));
+        self.put_strings(CF_BOT, DENYLIST_KEY, current).await
+    }
+
+    async fn get_announced_version(&self) -> Result<Option<String>> {
+        Ok(self.get_strings(CF_BOT, ANNOUNCED_VERSION_KEY).await?.pop())
+    }
+
+    async fn set_announced_version(&self, version: &str) -> Result<()> {
+        self.put_strings(CF_BOT, ANNOUNCED_VERSION_KEY, vec![version.to_owned()])
+            .await
+    }
+
+    async fn purge_user(&self, user_id: &UserId) -> Result<()> {
+        let db = self.db.clone();
+        let wincode_config = self.wincode_config;
+        let user_id = user_id.to_string();
+        spawn_blocking(move || {
+            db.delete(user_id.as_bytes())?;
+            for cf_name in COLUMN_FAMILIES {
+                if matches!(cf_name, CF_COLLECTION | CF_BOT | CF_AUDIT_LOG) {
+                    continue;
+                }
+                let cf = db.cf_handle(cf_name).unwrap();
+                db.delete_cf(&cf, user_id.as_bytes())?;
+            }
+            let cf = db.cf_handle(CF_FOLLOW).unwrap();
+            for item in db.iterator_cf(&cf, rocksdb::IteratorMode::Start) {
+                let (key, value) = item?;
+                let mut followed =
+                    config::deserialize::<Vec<String>, WincodeConfig>(&value, wincode_config)?;
+                if !followed.contains(&user_id) {
+                    continue;
+                }
+                followed.retain(|item| *item != user_id);
+                if followed.is_empty() {
+                    db.delete_cf(&cf, key)?;
+                } else {
+                    db.put_cf(&cf, key, config::serialize(&followed, wincode_config)?)?;
+                }
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn log_admin_action(&self, entry: AuditEntry) -> Result<()> {
+        let key = format!("{:020}-{}", entry.time, entry.admin);
+        let mut commands = self.get_strings(CF_AUDIT_LOG, &key).await?;
+        commands.push(entry.command);
+        self.put_strings(CF_AUDIT_LOG, &key, commands).await
+    }
+
+    async fn get_audit_log(&self, limit: usize) -> Result<Vec<AuditEntry>> {
+        let db = self.db.clone();
+        let wincode_config = self.wincode_config;
+        spawn_blocking(move || {
+            let cf = db.cf_handle(CF_AUDIT_LOG).unwrap();
+            let mut entries = Vec::new();
+            for item in db.iterator_cf(&cf, rocksdb::IteratorMode::End) {
+                if entries.len() >= limit {
+                    break;
+                }
+                let (key, value) = item?;
+                let key = String::from_utf8(key.to_vec())?;
+                let (time, admin) = key.split_at(20);
+                let time = time.parse::<u64>()?;
+                let commands =
+                    config::deserialize::<Vec<String>, WincodeConfig>(&value, wincode_config)?;
+                for command in commands.into_iter().rev() {
+                    if entries.len() >= limit {
+                        break;
+                    }
+                    entries.push(AuditEntry {
+                        time,
+                        admin: admin[1..].to_owned(),
+                        command,
+                    });
+                }
+            }
+            entries.reverse();
+            Ok(entries)
+        })
+        .await?
+    }
 }
@@ -1,8 +1,14 @@
 use deadpool_sqlite::{Config, Pool, Runtime};
 use eyre::Result;
 use matrix_sdk::ruma::{OwnedUserId, UserId};
+use rusqlite::OptionalExtension;
 use std::path::Path;
 
+use super::{
+    AuditEntry, Collection, Delivered, LoggedNotification, PackageConstraint, Preference,
+    PrereleaseOverride, SavedFilter, Snooze, Tag,
+};
+
 #[derive(Debug, Clone)]
 pub struct SqliteDatabase {
     pool: Pool,
@@ -27,6 +33,62 @@ impl super::Database for SqliteDatabase {
                     "CREATE TABLE IF NOT EXISTS notification ( user_id TEXT )",
                     (),
                 )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS snooze ( user_id TEXT NOT NULL, package TEXT NOT NULL, after TEXT NOT NULL, until INTEGER )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS delivered ( user_id TEXT NOT NULL, package TEXT NOT NULL, after TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS subscription_constraint ( user_id TEXT NOT NULL, package TEXT NOT NULL, constraint_spec TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS preference ( user_id TEXT NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS prerelease_override ( user_id TEXT NOT NULL, package TEXT NOT NULL, show INTEGER NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS saved_filter ( user_id TEXT NOT NULL, name TEXT NOT NULL, expression TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS collection ( name TEXT NOT NULL, package TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS collection_subscription ( user_id TEXT NOT NULL, collection TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS follow ( user_id TEXT NOT NULL, followed TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS package_tag ( user_id TEXT NOT NULL, tag TEXT NOT NULL, package TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS notification_log ( user_id TEXT NOT NULL, event_id TEXT NOT NULL, package TEXT NOT NULL, sent INTEGER NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS denylist ( entry TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS announced_version ( version TEXT NOT NULL )",
+                    (),
+                )?;
+                db_conn.execute(
+                    "CREATE TABLE IF NOT EXISTS audit_log ( time INTEGER NOT NULL, admin TEXT NOT NULL, command TEXT NOT NULL )",
+                    (),
+                )?;
                 Ok::<(), rusqlite::Error>(())
             })
             .await
@@ -178,4 +240,1045 @@ impl super::Database for SqliteDatabase {
             .await
             .unwrap()?)
     }
+
+    async fn subscribers(&self, package: &str) -> Result<Vec<OwnedUserId>> {
+        let db_conn = self.pool.get().await?;
+        let package = package.to_owned();
+        Ok(db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn
+                    .prepare("SELECT DISTINCT user_id FROM subscription WHERE package = ?1")?;
+                let mut rows = stmt.query([&package])?;
+
+                let mut users = Vec::new();
+                while let Some(row) = rows.next()? {
+                    users.push(UserId::parse(row.get::<_, String>(0)?).unwrap());
+                }
+                Ok::<Vec<OwnedUserId>, rusqlite::Error>(users)
+            })
+            .await
+            .unwrap()?)
+    }
+
+    async fn get_snoozes(&self, user_id: &UserId) -> Result<Vec<Snooze>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let snoozes = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn
+                    .prepare("SELECT package, after, until FROM snooze WHERE user_id = ?1")?;
+                let rows = stmt.query_map([&user_id_str], |row| {
+                    Ok(Snooze {
+                        package: row.get(0)?,
+                        after: row.get(1)?,
+                        until: row.get::<_, Option<i64>>(2)?.map(|until| until as u64),
+                    })
+                })?;
+                let mut snoozes = Vec::new();
+                for row in rows {
+                    snoozes.push(row?);
+                }
+                Ok::<Vec<Snooze>, rusqlite::Error>(snoozes)
+            })
+            .await
+            .unwrap()?;
+        Ok(snoozes)
+    }
+
+    async fn snooze(&self, user_id: &UserId, snoozes: Vec<Snooze>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt = transaction
+                    .prepare("DELETE FROM snooze WHERE user_id = ?1 AND package = ?2")?;
+                let mut insert_stmt = transaction.prepare(
+                    "INSERT INTO snooze (user_id, package, after, until) VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                for snooze in snoozes {
+                    delete_stmt.execute([&user_id_str, &snooze.package])?;
+                    insert_stmt.execute((
+                        &user_id_str,
+                        &snooze.package,
+                        &snooze.after,
+                        snooze.until.map(|until| until as i64),
+                    ))?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn unsnooze(&self, user_id: &UserId, packages: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction
+                    .prepare("DELETE FROM snooze WHERE user_id = ?1 AND package = ?2")?;
+                for package in packages {
+                    stmt.execute([&user_id_str, &package])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_delivered(&self, user_id: &UserId) -> Result<Vec<Delivered>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let delivered = db_conn
+            .interact(move |db_conn| {
+                let mut stmt =
+                    db_conn.prepare("SELECT package, after FROM delivered WHERE user_id = ?1")?;
+                let rows = stmt.query_map([&user_id_str], |row| {
+                    Ok(Delivered {
+                        package: row.get(0)?,
+                        after: row.get(1)?,
+                    })
+                })?;
+                let mut delivered = Vec::new();
+                for row in rows {
+                    delivered.push(row?);
+                }
+                Ok::<Vec<Delivered>, rusqlite::Error>(delivered)
+            })
+            .await
+            .unwrap()?;
+        Ok(delivered)
+    }
+
+    async fn mark_delivered(&self, user_id: &UserId, delivered: Vec<Delivered>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt = transaction
+                    .prepare("DELETE FROM delivered WHERE user_id = ?1 AND package = ?2")?;
+                let mut insert_stmt = transaction.prepare(
+                    "INSERT INTO delivered (user_id, package, after) VALUES (?1, ?2, ?3)",
+                )?;
+                for item in delivered {
+                    delete_stmt.execute([&user_id_str, &item.package])?;
+                    insert_stmt.execute([&user_id_str, &item.package, &item.after])?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn clear_delivered(&self, user_id: &UserId, packages: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction
+                    .prepare("DELETE FROM delivered WHERE user_id = ?1 AND package = ?2")?;
+                for package in packages {
+                    stmt.execute([&user_id_str, &package])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_constraints(&self, user_id: &UserId) -> Result<Vec<PackageConstraint>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let constraints = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare(
+                    "SELECT package, constraint_spec FROM subscription_constraint WHERE user_id = ?1",
+                )?;
+                let rows = stmt.query_map([&user_id_str], |row| {
+                    Ok(PackageConstraint {
+                        package: row.get(0)?,
+                        constraint: row.get(1)?,
+                    })
+                })?;
+                let mut constraints = Vec::new();
+                for row in rows {
+                    constraints.push(row?);
+                }
+                Ok::<Vec<PackageConstraint>, rusqlite::Error>(constraints)
+            })
+            .await
+            .unwrap()?;
+        Ok(constraints)
+    }
+
+    async fn set_constraints(
+        &self,
+        user_id: &UserId,
+        constraints: Vec<PackageConstraint>,
+    ) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt = transaction.prepare(
+                    "DELETE FROM subscription_constraint WHERE user_id = ?1 AND package = ?2",
+                )?;
+                let mut insert_stmt = transaction.prepare(
+                    "INSERT INTO subscription_constraint (user_id, package, constraint_spec) VALUES (?1, ?2, ?3)",
+                )?;
+                for constraint in constraints {
+                    delete_stmt.execute([&user_id_str, &constraint.package])?;
+                    insert_stmt.execute([
+                        &user_id_str,
+                        &constraint.package,
+                        &constraint.constraint,
+                    ])?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn clear_constraints(&self, user_id: &UserId, packages: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction.prepare(
+                    "DELETE FROM subscription_constraint WHERE user_id = ?1 AND package = ?2",
+                )?;
+                for package in packages {
+                    stmt.execute([&user_id_str, &package])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_preference(
+        &self,
+        user_id: &UserId,
+        preference: Preference,
+    ) -> Result<Option<String>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let value = db_conn
+            .interact(move |db_conn| {
+                db_conn
+                    .query_row(
+                        "SELECT value FROM preference WHERE user_id = ?1 AND key = ?2",
+                        [user_id_str.as_str(), preference.key()],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .optional()
+            })
+            .await
+            .unwrap()?;
+        Ok(value)
+    }
+
+    async fn set_preference(
+        &self,
+        user_id: &UserId,
+        preference: Preference,
+        value: Option<String>,
+    ) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                transaction.execute(
+                    "DELETE FROM preference WHERE user_id = ?1 AND key = ?2",
+                    [user_id_str.as_str(), preference.key()],
+                )?;
+                if let Some(value) = value {
+                    transaction.execute(
+                        "INSERT INTO preference (user_id, key, value) VALUES (?1, ?2, ?3)",
+                        [user_id_str.as_str(), preference.key(), value.as_str()],
+                    )?;
+                }
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn users_with_preference(
+        &self,
+        preference: Preference,
+        value: &str,
+    ) -> Result<Vec<OwnedUserId>> {
+        let db_conn = self.pool.get().await?;
+        let value = value.to_owned();
+        Ok(db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare(
+                    "SELECT DISTINCT user_id FROM preference WHERE key = ?1 AND value = ?2",
+                )?;
+                let mut rows = stmt.query([preference.key(), value.as_str()])?;
+
+                let mut users = Vec::new();
+                while let Some(row) = rows.next()? {
+                    users.push(UserId::parse(row.get::<_, String>(0)?).unwrap());
+                }
+                Ok::<Vec<OwnedUserId>, rusqlite::Error>(users)
+            })
+            .await
+            .unwrap()?)
+    }
+
+    async fn get_prerelease_overrides(&self, user_id: &UserId) -> Result<Vec<PrereleaseOverride>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let overrides = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn
+                    .prepare("SELECT package, show FROM prerelease_override WHERE user_id = ?1")?;
+                let rows = stmt.query_map([&user_id_str], |row| {
+                    Ok(PrereleaseOverride {
+                        package: row.get(0)?,
+                        show: row.get(1)?,
+                    })
+                })?;
+                let mut overrides = Vec::new();
+                for row in rows {
+                    overrides.push(row?);
+                }
+                Ok::<Vec<PrereleaseOverride>, rusqlite::Error>(overrides)
+            })
+            .await
+            .unwrap()?;
+        Ok(overrides)
+    }
+
+    async fn set_prerelease_overrides(
+        &self,
+        user_id: &UserId,
+        overrides: Vec<PrereleaseOverride>,
+    ) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt = transaction.prepare(
+                    "DELETE FROM prerelease_override WHERE user_id = ?1 AND package = ?2",
+                )?;
+                let mut insert_stmt = transaction.prepare(
+                    "INSERT INTO prerelease_override (user_id, package, show) VALUES (?1, ?2, ?3)",
+                )?;
+                for item in overrides {
+                    delete_stmt.execute([&user_id_str, &item.package])?;
+                    insert_stmt.execute((&user_id_str, &item.package, item.show))?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn clear_prerelease_overrides(
+        &self,
+        user_id: &UserId,
+        packages: Vec<String>,
+    ) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction.prepare(
+                    "DELETE FROM prerelease_override WHERE user_id = ?1 AND package = ?2",
+                )?;
+                for package in packages {
+                    stmt.execute([&user_id_str, &package])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_filters(&self, user_id: &UserId) -> Result<Vec<SavedFilter>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let filters = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn
+                    .prepare("SELECT name, expression FROM saved_filter WHERE user_id = ?1")?;
+                let rows = stmt.query_map([&user_id_str], |row| {
+                    Ok(SavedFilter {
+                        name: row.get(0)?,
+                        expression: row.get(1)?,
+                    })
+                })?;
+                let mut filters = Vec::new();
+                for row in rows {
+                    filters.push(row?);
+                }
+                Ok::<Vec<SavedFilter>, rusqlite::Error>(filters)
+            })
+            .await
+            .unwrap()?;
+        Ok(filters)
+    }
+
+    async fn add_filter(&self, user_id: &UserId, filter: SavedFilter) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                transaction.execute(
+                    "DELETE FROM saved_filter WHERE user_id = ?1 AND name = ?2",
+                    [&user_id_str, &filter.name],
+                )?;
+                transaction.execute(
+                    "INSERT INTO saved_filter (user_id, name, expression) VALUES (?1, ?2, ?3)",
+                    [&user_id_str, &filter.name, &filter.expression],
+                )?;
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn remove_filters(&self, user_id: &UserId, names: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction
+                    .prepare("DELETE FROM saved_filter WHERE user_id = ?1 AND name = ?2")?;
+                for name in names {
+                    stmt.execute([&user_id_str, &name])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_collections(&self) -> Result<Vec<Collection>> {
+        let db_conn = self.pool.get().await?;
+        let collections = db_conn
+            .interact(|db_conn| {
+                let mut stmt = db_conn
+                    .prepare("SELECT name, package FROM collection ORDER BY name, package")?;
+                let rows = stmt.query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?;
+                let mut collections: Vec<Collection> = Vec::new();
+                for row in rows {
+                    let (name, package) = row?;
+                    match collections.last_mut() {
+                        Some(collection) if collection.name == name => {
+                            collection.packages.push(package)
+                        }
+                        _ => collections.push(Collection {
+                            name,
+                            packages: vec![package],
+                        }),
+                    }
+                }
+                Ok::<Vec<Collection>, rusqlite::Error>(collections)
+            })
+            .await
+            .unwrap()?;
+        Ok(collections)
+    }
+
+    async fn add_to_collection(&self, name: &str, packages: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let name = name.to_owned();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt = transaction
+                    .prepare("DELETE FROM collection WHERE name = ?1 AND package = ?2")?;
+                let mut insert_stmt = transaction
+                    .prepare("INSERT INTO collection (name, package) VALUES (?1, ?2)")?;
+                for package in packages {
+                    delete_stmt.execute([&name, &package])?;
+                    insert_stmt.execute([&name, &package])?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn remove_from_collection(&self, name: &str, packages: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let name = name.to_owned();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction
+                    .prepare("DELETE FROM collection WHERE name = ?1 AND package = ?2")?;
+                for package in packages {
+                    stmt.execute([&name, &package])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let name = name.to_owned();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                transaction.execute("DELETE FROM collection WHERE name = ?1", [&name])?;
+                transaction.execute(
+                    "DELETE FROM collection_subscription WHERE collection = ?1",
+                    [&name],
+                )?;
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_collection_subscriptions(&self, user_id: &UserId) -> Result<Vec<String>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let names = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn
+                    .prepare("SELECT collection FROM collection_subscription WHERE user_id = ?1")?;
+                let rows = stmt.query_map([&user_id_str], |row| row.get(0))?;
+                let mut names: Vec<String> = Vec::new();
+                for row in rows {
+                    names.push(row?);
+                }
+                Ok::<Vec<String>, rusqlite::Error>(names)
+            })
+            .await
+            .unwrap()?;
+        Ok(names)
+    }
+
+    async fn subscribe_collections(&self, user_id: &UserId, names: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt = transaction.prepare(
+                    "DELETE FROM collection_subscription WHERE user_id = ?1 AND collection = ?2",
+                )?;
+                let mut insert_stmt = transaction.prepare(
+                    "INSERT INTO collection_subscription (user_id, collection) VALUES (?1, ?2)",
+                )?;
+                for name in names {
+                    delete_stmt.execute([&user_id_str, &name])?;
+                    insert_stmt.execute([&user_id_str, &name])?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn unsubscribe_collections(&self, user_id: &UserId, names: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction.prepare(
+                    "DELETE FROM collection_subscription WHERE user_id = ?1 AND collection = ?2",
+                )?;
+                for name in names {
+                    stmt.execute([&user_id_str, &name])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn collection_subscribers(&self, name: &str) -> Result<Vec<OwnedUserId>> {
+        let db_conn = self.pool.get().await?;
+        let name = name.to_owned();
+        Ok(db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare(
+                    "SELECT DISTINCT user_id FROM collection_subscription WHERE collection = ?1",
+                )?;
+                let mut rows = stmt.query([&name])?;
+
+                let mut users = Vec::new();
+                while let Some(row) = rows.next()? {
+                    users.push(UserId::parse(row.get::<_, String>(0)?).unwrap());
+                }
+                Ok::<Vec<OwnedUserId>, rusqlite::Error>(users)
+            })
+            .await
+            .unwrap()?)
+    }
+
+    async fn get_follows(&self, user_id: &UserId) -> Result<Vec<OwnedUserId>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let followed = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare("SELECT followed FROM follow WHERE user_id = ?1")?;
+                let mut rows = stmt.query([&user_id_str])?;
+
+                let mut followed = Vec::new();
+                while let Some(row) = rows.next()? {
+                    followed.push(UserId::parse(row.get::<_, String>(0)?).unwrap());
+                }
+                Ok::<Vec<OwnedUserId>, rusqlite::Error>(followed)
+            })
+            .await
+            .unwrap()?;
+        Ok(followed)
+    }
+
+    async fn follow(&self, user_id: &UserId, followed: &UserId) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let followed_str = followed.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                transaction.execute(
+                    "DELETE FROM follow WHERE user_id = ?1 AND followed = ?2",
+                    [&user_id_str, &followed_str],
+                )?;
+                transaction.execute(
+                    "INSERT INTO follow (user_id, followed) VALUES (?1, ?2)",
+                    [&user_id_str, &followed_str],
+                )?;
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn unfollow(&self, user_id: &UserId, followed: Vec<OwnedUserId>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction
+                    .prepare("DELETE FROM follow WHERE user_id = ?1 AND followed = ?2")?;
+                for followed in followed {
+                    stmt.execute([user_id_str.as_str(), followed.as_str()])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_tags(&self, user_id: &UserId) -> Result<Vec<Tag>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let tags = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare(
+                    "SELECT tag, package FROM package_tag WHERE user_id = ?1 ORDER BY tag, package",
+                )?;
+                let rows = stmt.query_map([&user_id_str], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?;
+                let mut tags: Vec<Tag> = Vec::new();
+                for row in rows {
+                    let (name, package) = row?;
+                    match tags.last_mut() {
+                        Some(tag) if tag.name == name => tag.packages.push(package),
+                        _ => tags.push(Tag {
+                            name,
+                            packages: vec![package],
+                        }),
+                    }
+                }
+                Ok::<Vec<Tag>, rusqlite::Error>(tags)
+            })
+            .await
+            .unwrap()?;
+        Ok(tags)
+    }
+
+    async fn add_to_tag(&self, user_id: &UserId, name: &str, packages: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let name = name.to_owned();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt = transaction.prepare(
+                    "DELETE FROM package_tag WHERE user_id = ?1 AND tag = ?2 AND package = ?3",
+                )?;
+                let mut insert_stmt = transaction.prepare(
+                    "INSERT INTO package_tag (user_id, tag, package) VALUES (?1, ?2, ?3)",
+                )?;
+                for package in packages {
+                    delete_stmt.execute([&user_id_str, &name, &package])?;
+                    insert_stmt.execute([&user_id_str, &name, &package])?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn remove_from_tag(
+        &self,
+        user_id: &UserId,
+        name: &str,
+        packages: Vec<String>,
+    ) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let name = name.to_owned();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction.prepare(
+                    "DELETE FROM package_tag WHERE user_id = ?1 AND tag = ?2 AND package = ?3",
+                )?;
+                for package in packages {
+                    stmt.execute([&user_id_str, &name, &package])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn delete_tag(&self, user_id: &UserId, name: &str) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let name = name.to_owned();
+        db_conn
+            .interact(move |db_conn| {
+                db_conn.execute(
+                    "DELETE FROM package_tag WHERE user_id = ?1 AND tag = ?2",
+                    [&user_id_str, &name],
+                )?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_notification_log(&self, user_id: &UserId) -> Result<Vec<LoggedNotification>> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        let log = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare(
+                    "SELECT event_id, package, sent FROM notification_log WHERE user_id = ?1 ORDER BY sent, event_id, package",
+                )?;
+                let rows = stmt.query_map([&user_id_str], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)? as u64,
+                    ))
+                })?;
+                let mut log: Vec<LoggedNotification> = Vec::new();
+                for row in rows {
+                    let (event_id, package, sent) = row?;
+                    match log.last_mut() {
+                        Some(notification) if notification.event_id == event_id => {
+                            notification.packages.push(package)
+                        }
+                        _ => log.push(LoggedNotification {
+                            event_id,
+                            packages: vec![package],
+                            sent,
+                        }),
+                    }
+                }
+                Ok::<Vec<LoggedNotification>, rusqlite::Error>(log)
+            })
+            .await
+            .unwrap()?;
+        Ok(log)
+    }
+
+    async fn log_notification(
+        &self,
+        user_id: &UserId,
+        notification: LoggedNotification,
+        expire_before: u64,
+    ) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                transaction.execute(
+                    "DELETE FROM notification_log WHERE user_id = ?1 AND sent < ?2",
+                    (&user_id_str, expire_before as i64),
+                )?;
+                let mut stmt = transaction.prepare(
+                    "INSERT INTO notification_log (user_id, event_id, package, sent) VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                for package in notification.packages {
+                    stmt.execute((
+                        &user_id_str,
+                        &notification.event_id,
+                        &package,
+                        notification.sent as i64,
+                    ))?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_denylist(&self) -> Result<Vec<String>> {
+        let db_conn = self.pool.get().await?;
+        let entries = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare("SELECT entry FROM denylist")?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                let mut entries: Vec<String> = Vec::new();
+                for row in rows {
+                    entries.push(row?);
+                }
+                Ok::<Vec<String>, rusqlite::Error>(entries)
+            })
+            .await
+            .unwrap()?;
+        Ok(entries)
+    }
+
+    async fn deny(&self, entries: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut delete_stmt =
+                    transaction.prepare("DELETE FROM denylist WHERE entry = ?1")?;
+                let mut insert_stmt =
+                    transaction.prepare("INSERT INTO denylist (entry) VALUES (?1)")?;
+                for entry in entries {
+                    delete_stmt.execute([&entry])?;
+                    insert_stmt.execute([&entry])?;
+                }
+                drop(delete_stmt);
+                drop(insert_stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn undeny(&self, entries: Vec<String>) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                let mut stmt = transaction.prepare("DELETE FROM denylist WHERE entry = ?1")?;
+                for entry in entries {
+                    stmt.execute([&entry])?;
+                }
+                drop(stmt);
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_announced_version(&self) -> Result<Option<String>> {
+        let db_conn = self.pool.get().await?;
+        let version = db_conn
+            .interact(|db_conn| {
+                db_conn
+                    .query_row("SELECT version FROM announced_version", [], |row| {
+                        row.get::<_, String>(0)
+                    })
+                    .optional()
+            })
+            .await
+            .unwrap()?;
+        Ok(version)
+    }
+
+    async fn set_announced_version(&self, version: &str) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let version = version.to_owned();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                transaction.execute("DELETE FROM announced_version", ())?;
+                transaction.execute(
+                    "INSERT INTO announced_version (version) VALUES (?1)",
+                    [&version],
+                )?;
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn purge_user(&self, user_id: &UserId) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        let user_id_str = user_id.to_string();
+        db_conn
+            .interact(move |db_conn| {
+                let transaction = db_conn.transaction()?;
+                for table in [
+                    "subscription",
+                    "notification",
+                    "snooze",
+                    "delivered",
+                    "subscription_constraint",
+                    "preference",
+                    "prerelease_override",
+                    "saved_filter",
+                    "collection_subscription",
+                    "follow",
+                    "package_tag",
+                    "notification_log",
+                ] {
+                    transaction.execute(
+                        &format!("DELETE FROM {table} WHERE user_id = ?1"),
+                        [&user_id_str],
+                    )?;
+                }
+                transaction.execute("DELETE FROM follow WHERE followed = ?1", [&user_id_str])?;
+                transaction.commit()?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn log_admin_action(&self, entry: AuditEntry) -> Result<()> {
+        let db_conn = self.pool.get().await?;
+        db_conn
+            .interact(move |db_conn| {
+                db_conn.execute(
+                    "INSERT INTO audit_log (time, admin, command) VALUES (?1, ?2, ?3)",
+                    (entry.time as i64, &entry.admin, &entry.command),
+                )?;
+                Ok::<(), rusqlite::Error>(())
+            })
+            .await
+            .unwrap()?;
+        Ok(())
+    }
+
+    async fn get_audit_log(&self, limit: usize) -> Result<Vec<AuditEntry>> {
+        let db_conn = self.pool.get().await?;
+        let mut entries = db_conn
+            .interact(move |db_conn| {
+                let mut stmt = db_conn.prepare(
+                    "SELECT time, admin, command FROM audit_log ORDER BY rowid DESC LIMIT ?1",
+                )?;
+                let rows = stmt.query_map([limit as i64], |row| {
+                    Ok(AuditEntry {
+                        time: row.get::<_, i64>(0)? as u64,
+                        admin: row.get(1)?,
+                        command: row.get(2)?,
+                    })
+                })?;
+                let mut entries: Vec<AuditEntry> = Vec::new();
+                for row in rows {
+                    entries.push(row?);
+                }
+                Ok::<Vec<AuditEntry>, rusqlite::Error>(entries)
+            })
+            .await
+            .unwrap()?;
+        entries.reverse();
+        Ok(entries)
+    }
 }
@@ -0,0 +1,84 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const UNITS: [(char, u64); 5] = [
+    ('w', 7 * 24 * 3600),
+    ('d', 24 * 3600),
+    ('h', 3600),
+    ('m', 60),
+    ('s', 1),
+];
+
+/// Current UNIX timestamp in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Parse durations like `30m`, `12h`, `7d`, `2w` or combinations such as `1d12h`.
+pub fn parse(text: &str) -> Option<Duration> {
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let (_, secs) = UNITS
+            .iter()
+            .find(|(unit, _)| *unit == c.to_ascii_lowercase())?;
+        let value = number.parse::<u64>().ok()?;
+        total = total.checked_add(value.checked_mul(*secs)?)?;
+        number.clear();
+    }
+    if !number.is_empty() || total == 0 {
+        return None;
+    }
+    Some(Duration::from_secs(total))
+}
+
+/// Format a duration with its two most significant units, e.g. `1d 12h`.
+pub fn format(duration: Duration) -> String {
+    let mut secs = duration.as_secs();
+    let mut parts = Vec::new();
+    for (unit, unit_secs) in UNITS.iter().skip(1) {
+        if secs >= *unit_secs {
+            parts.push(format!("{}{}", secs / unit_secs, unit));
+            secs %= unit_secs;
+        }
+        if parts.len() == 2 {
+            break;
+        }
+    }
+    if parts.is_empty() {
+        "0s".to_owned()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("30m"), Some(Duration::from_secs(1800)));
+        assert_eq!(parse("7d"), Some(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse("1d12h"), Some(Duration::from_secs(36 * 3600)));
+        assert_eq!(parse("2W"), Some(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse("12"), None);
+        assert_eq!(parse("0d"), None);
+        assert_eq!(parse("3y"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(Duration::from_secs(36 * 3600)), "1d 12h");
+        assert_eq!(format(Duration::from_secs(14 * 86400 + 61)), "14d 1m");
+        assert_eq!(format(Duration::from_secs(59)), "59s");
+        assert_eq!(format(Duration::ZERO), "0s");
+    }
+}
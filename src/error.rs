@@ -0,0 +1,108 @@
+//! Replies to commands that failed.
+//!
+//! Errors caused by what the user sent are explained to them. Any other error is logged under a short correlation id,
+//! which the user is given so the admins can find the log entry, and reported to the admins if the config asks for it.
+
+use eyre::Report;
+use matrix_sdk::{
+    Client,
+    ruma::{IdParseError, UserId, events::room::message::RoomMessageEventContent},
+};
+use std::{
+    hash::{BuildHasher, RandomState},
+    time::SystemTime,
+};
+use tracing::{error, warn};
+
+use crate::{
+    bot::{dm_or_create, user_lang},
+    config::Config,
+    db::DatabaseImpl,
+    i18n::{Lang, t},
+};
+
+/// The explanation of `error` if the user caused it.
+fn user_message(lang: Lang, error: &Report) -> Option<String> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<IdParseError>())
+        .map(|e| t!(lang, "error.invalid_id", error = e))
+}
+
+fn correlation_id() -> String {
+    format!(
+        "{:08x}",
+        RandomState::new().hash_one(SystemTime::now()) & 0xffffffff
+    )
+}
+
+/// The reply to `command` of `user_id` which failed with `error`.
+pub async fn error_reply(
+    client: &Client,
+    config: &Config,
+    db: &DatabaseImpl,
+    user_id: &UserId,
+    command: &str,
+    error: Report,
+) -> RoomMessageEventContent {
+    // The database may be what failed, so the language falls back to the default one.
+    let lang = user_lang(db, user_id).await.unwrap_or_default();
+    if let Some(message) = user_message(lang, &error) {
+        return RoomMessageEventContent::notice_plain(message);
+    }
+    let id = correlation_id();
+    error!(
+        "Error {} while handling {:?} from {}: {:?}",
+        id, command, user_id, error
+    );
+    if config.report_errors {
+        report(
+            client.clone(),
+            config.clone(),
+            db.clone(),
+            &id,
+            user_id,
+            command,
+            &error,
+        );
+    }
+    RoomMessageEventContent::notice_plain(t!(lang, "error.internal", id = id))
+}
+
+/// Send the error to every admin in a direct message.
+fn report(
+    client: Client,
+    config: Config,
+    db: DatabaseImpl,
+    id: &str,
+    user_id: &UserId,
+    command: &str,
+    error: &Report,
+) {
+    let (id, user_id, command, error) = (
+        id.to_owned(),
+        user_id.to_owned(),
+        command.to_owned(),
+        format!("{error:#}"),
+    );
+    tokio::spawn(async move {
+        for admin in config.admin_ids.iter() {
+            let lang = user_lang(&db, admin).await.unwrap_or_default();
+            let content = RoomMessageEventContent::notice_plain(t!(
+                lang,
+                "error.report",
+                id = id,
+                command = command,
+                user = user_id,
+                error = error
+            ));
+            let sent = match dm_or_create(client.clone(), admin).await {
+                Ok(room) => room.send(content).await.map(|_| ()).map_err(Report::from),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = sent {
+                warn!("Unable to report error {} to {}: {}", id, admin, e);
+            }
+        }
+    });
+}
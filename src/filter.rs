@@ -0,0 +1,416 @@
+//! A small expression language for selecting packages from the anicca feed, e.g.
+//! `path ^= "lang-" and not warning(prerelease) and bump >= minor`.
+//!
+//! ```text
+//! expr      := and ("or" and)*
+//! and       := unary ("and" unary)*
+//! unary     := "not" unary | primary
+//! primary   := "(" expr ")" | "prerelease" | "warning" "(" value ")" | field op value
+//! field     := "name" | "path" | "before" | "after" | "bump"
+//! op        := "==" | "!=" | ">" | ">=" | "<" | "<=" | "^=" | "$=" | "~="
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use eyre::{Result, bail, eyre};
+
+use crate::{
+    anicca::Package,
+    version::{BumpKind, Op, Version},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Path,
+    Before,
+    After,
+    Bump,
+}
+
+impl FromStr for Field {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "name" => Ok(Self::Name),
+            "path" => Ok(Self::Path),
+            "before" => Ok(Self::Before),
+            "after" => Ok(Self::After),
+            "bump" => Ok(Self::Bump),
+            _ => Err(eyre!("Unknown field: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Order(Op),
+    StartsWith,
+    EndsWith,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Text(Field, CmpOp, String),
+    Bump(Op, BumpKind),
+    Warning(String),
+    Prerelease,
+}
+
+impl Expr {
+    pub fn matches(&self, package: &Package) -> bool {
+        match self {
+            Self::And(a, b) => a.matches(package) && b.matches(package),
+            Self::Or(a, b) => a.matches(package) || b.matches(package),
+            Self::Not(a) => !a.matches(package),
+            Self::Text(field, op, value) => {
+                let text = match field {
+                    Field::Name => &package.name,
+                    Field::Path => &package.path,
+                    Field::Before => &package.before,
+                    Field::After => &package.after,
+                    Field::Bump => unreachable!(),
+                };
+                match op {
+                    CmpOp::StartsWith => text.starts_with(value.as_str()),
+                    CmpOp::EndsWith => text.ends_with(value.as_str()),
+                    CmpOp::Contains => text.contains(value.as_str()),
+                    CmpOp::Order(op) if matches!(field, Field::Before | Field::After) => {
+                        op.test(Version::parse(text).cmp(&Version::parse(value)))
+                    }
+                    CmpOp::Order(op) => op.test(text.as_str().cmp(value.as_str())),
+                }
+            }
+            Self::Bump(op, kind) => op.test(
+                Version::parse(&package.before)
+                    .bump_to(&Version::parse(&package.after))
+                    .cmp(kind),
+            ),
+            Self::Warning(warning) => {
+                let warning = warning.to_lowercase();
+                package
+                    .warnings
+                    .iter()
+                    .any(|w| w.to_lowercase().contains(&warning))
+            }
+            Self::Prerelease => Version::parse(&package.after).is_prerelease(),
+        }
+    }
+}
+
+/// A parsed filter expression that remembers its source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn matches(&self, package: &Package) -> bool {
+        self.expr.matches(package)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let expr = parser.expr(0)?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {token}");
+        }
+        Ok(Self {
+            source: s.trim().to_owned(),
+            expr,
+        })
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "`{word}`"),
+            Self::Str(s) => write!(f, "\"{s}\""),
+            Self::Op(op) => write!(f, "`{op}`"),
+            Self::LParen => f.write_str("`(`"),
+            Self::RParen => f.write_str("`)`"),
+        }
+    }
+}
+
+const OPERATORS: [&str; 9] = ["==", "!=", ">=", "<=", "^=", "$=", "~=", ">", "<"];
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::LParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::RParen);
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped)) => s.push(escaped),
+                        None => bail!("Unterminated string"),
+                    },
+                    Some((_, end)) if end == c => break,
+                    Some((_, other)) => s.push(other),
+                    None => bail!("Unterminated string"),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else if let Some(op) = OPERATORS.iter().find(|op| text[i..].starts_with(**op)) {
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Op(op));
+        } else {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_alphanumeric() || "-_.+:".contains(c) {
+                    word.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if word.is_empty() {
+                bail!("Unexpected character `{c}`");
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+/// How deep expressions may nest, counting parentheses, `not` and every `and`/`or`, so text sent by users cannot
+/// overflow the stack while parsing or matching.
+const MAX_DEPTH: usize = 32;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn nest(depth: usize) -> Result<usize> {
+        if depth >= MAX_DEPTH {
+            bail!("Expression nested too deeply");
+        }
+        Ok(depth + 1)
+    }
+
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(word))
+    }
+
+    fn advance(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| eyre!("Unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        let token = self.advance()?;
+        if token != expected {
+            bail!("Expected {expected}, found {token}");
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<String> {
+        match self.advance()? {
+            Token::Word(s) | Token::Str(s) => Ok(s),
+            token => bail!("Expected a value, found {token}"),
+        }
+    }
+
+    fn expr(&mut self, mut depth: usize) -> Result<Expr> {
+        let mut expr = self.and(depth)?;
+        while self.peek_word("or") {
+            self.pos += 1;
+            depth = Self::nest(depth)?;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and(depth)?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self, mut depth: usize) -> Result<Expr> {
+        let mut expr = self.unary(depth)?;
+        while self.peek_word("and") {
+            self.pos += 1;
+            depth = Self::nest(depth)?;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary(depth)?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<Expr> {
+        if self.peek_word("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary(Self::nest(depth)?)?)));
+        }
+        self.primary(depth)
+    }
+
+    fn primary(&mut self, depth: usize) -> Result<Expr> {
+        let word = match self.advance()? {
+            Token::LParen => {
+                let expr = self.expr(Self::nest(depth)?)?;
+                self.expect(Token::RParen)?;
+                return Ok(expr);
+            }
+            Token::Word(word) => word.to_ascii_lowercase(),
+            token => bail!("Expected a field, found {token}"),
+        };
+        match word.as_str() {
+            "prerelease" => return Ok(Expr::Prerelease),
+            "warning" => {
+                self.expect(Token::LParen)?;
+                let warning = self.value()?;
+                self.expect(Token::RParen)?;
+                return Ok(Expr::Warning(warning));
+            }
+            _ => (),
+        }
+
+        let field: Field = word.parse()?;
+        let op = match self.advance()? {
+            Token::Op("^=") => CmpOp::StartsWith,
+            Token::Op("$=") => CmpOp::EndsWith,
+            Token::Op("~=") => CmpOp::Contains,
+            Token::Op(op) => CmpOp::Order(Op::split(op).unwrap().0),
+            token => bail!("Expected an operator, found {token}"),
+        };
+        let value = self.value()?;
+        match (field, op) {
+            (Field::Bump, CmpOp::Order(op)) => Ok(Expr::Bump(op, value.parse()?)),
+            (Field::Bump, _) => bail!("`bump` can only be compared with ==, !=, <, <=, > or >="),
+            (Field::Name | Field::Path, CmpOp::Order(op)) if !matches!(op, Op::Eq | Op::Ne) => {
+                bail!("`{word}` can only be compared with ==, !=, ^=, $= or ~=")
+            }
+            _ => Ok(Expr::Text(field, op, value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, path: &str, before: &str, after: &str) -> Package {
+        Package {
+            name: name.to_owned(),
+            before: before.to_owned(),
+            after: after.to_owned(),
+            path: path.to_owned(),
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let filter: Filter =
+            "path ^= \"lang-\" and not warning(prerelease) and bump >= minor".parse()?;
+        assert_eq!(
+            filter.expr,
+            Expr::And(
+                Box::new(Expr::And(
+                    Box::new(Expr::Text(
+                        Field::Path,
+                        CmpOp::StartsWith,
+                        "lang-".to_owned()
+                    )),
+                    Box::new(Expr::Not(Box::new(Expr::Warning("prerelease".to_owned())))),
+                )),
+                Box::new(Expr::Bump(Op::Ge, BumpKind::Minor)),
+            )
+        );
+        assert!("name".parse::<Filter>().is_err());
+        assert!("name == ".parse::<Filter>().is_err());
+        assert!("(name == foo".parse::<Filter>().is_err());
+        assert!("name == foo bar".parse::<Filter>().is_err());
+        assert!("bump ^= major".parse::<Filter>().is_err());
+        assert!("name > foo".parse::<Filter>().is_err());
+        assert!("size > 1".parse::<Filter>().is_err());
+        assert!("name == \"foo".parse::<Filter>().is_err());
+
+        assert!("((not (name == foo)))".parse::<Filter>().is_ok());
+        let nested = format!("{}name == foo{}", "(".repeat(10000), ")".repeat(10000));
+        assert!(nested.parse::<Filter>().is_err());
+        assert!(
+            format!("{}prerelease", "not ".repeat(10000))
+                .parse::<Filter>()
+                .is_err()
+        );
+        assert!(
+            format!("{}prerelease", "prerelease and ".repeat(10000))
+                .parse::<Filter>()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches() -> Result<()> {
+        let rust = package("rust", "lang-rust/rust", "1.86.0", "1.87.0");
+        let llvm = package("llvm", "runtime-devel/llvm", "19.1.7", "20.1.0-rc1");
+
+        let filter: Filter = "path ^= lang- and bump >= minor".parse()?;
+        assert!(filter.matches(&rust));
+        assert!(!filter.matches(&llvm));
+
+        let filter: Filter = "not prerelease and (name == llvm or after >= 1.87)".parse()?;
+        assert!(filter.matches(&rust));
+        assert!(!filter.matches(&llvm));
+
+        let filter: Filter = "bump == major or name ~= ust".parse()?;
+        assert!(filter.matches(&rust));
+        assert!(filter.matches(&llvm));
+
+        let mut warned = rust.clone();
+        warned.warnings = vec!["".to_owned(), "Pre-release version".to_owned()];
+        let filter: Filter = "warning(pre-release)".parse()?;
+        assert!(filter.matches(&warned));
+        assert!(!filter.matches(&rust));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use tokio::fs;
+
+use crate::{anicca::Anicca, duration};
+
+const HISTORY_FILENAME: &str = "anicca_history.json";
+
+/// What was last seen of a package in the anicca feed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub path: String,
+    pub after: String,
+    /// When `after` first showed up in the feed.
+    pub first_seen: u64,
+    /// The last fetch the package was part of.
+    pub last_seen: u64,
+}
+
+/// Every package that ever appeared in the anicca feed, kept across fetches.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History(BTreeMap<String, HistoryEntry>);
+
+impl History {
+    pub async fn load(data_dir: &Path) -> Result<Self> {
+        let file_path = data_dir.join(HISTORY_FILENAME);
+        if !file_path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(file_path).await?)?)
+    }
+
+    pub async fn save(&self, data_dir: &Path) -> Result<()> {
+        fs::write(
+            data_dir.join(HISTORY_FILENAME),
+            serde_json::to_string(&self.0)?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Record a fetched feed, restarting the pending time of packages whose target version changed.
+    pub fn record(&mut self, data: &Anicca, now: u64) {
+        for pkg in data.packages() {
+            match self.0.get_mut(&pkg.name) {
+                Some(entry) => {
+                    if entry.after != pkg.after {
+                        entry.after = pkg.after.clone();
+                        entry.first_seen = now;
+                    }
+                    entry.path = pkg.path.clone();
+                    entry.last_seen = now;
+                }
+                None => {
+                    self.0.insert(
+                        pkg.name.clone(),
+                        HistoryEntry {
+                            path: pkg.path.clone(),
+                            after: pkg.after.clone(),
+                            first_seen: now,
+                            last_seen: now,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&HistoryEntry> {
+        self.0.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(|name| name.as_str())
+    }
+
+    /// Seconds since the current update of `name` showed up, if it is still the one in the history.
+    pub fn pending_for(&self, name: &str, after: &str) -> Option<u64> {
+        self.0
+            .get(name)
+            .filter(|entry| entry.after == after)
+            .map(|entry| duration::now().saturating_sub(entry.first_seen))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anicca::Package;
+
+    fn feed(packages: &[(&str, &str)]) -> Anicca {
+        serde_json::from_value(serde_json::json!(
+            packages
+                .iter()
+                .map(|(name, after)| Package {
+                    name: name.to_string(),
+                    before: "1.0".to_owned(),
+                    after: after.to_string(),
+                    path: format!("app-utils/{name}"),
+                    warnings: Vec::new(),
+                })
+                .collect::<Vec<Package>>()
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_record() {
+        let mut history = History::default();
+        history.record(&feed(&[("foo", "2.0"), ("bar", "3.0")]), 100);
+        history.record(&feed(&[("foo", "2.0"), ("bar", "3.1")]), 200);
+        history.record(&feed(&[("foo", "2.0")]), 300);
+
+        let foo = history.get("foo").unwrap();
+        assert_eq!((foo.first_seen, foo.last_seen), (100, 300));
+        let bar = history.get("bar").unwrap();
+        assert_eq!(
+            (bar.after.as_str(), bar.first_seen, bar.last_seen),
+            ("3.1", 200, 200)
+        );
+        assert_eq!(history.names().collect::<Vec<&str>>(), ["bar", "foo"]);
+        assert!(history.pending_for("bar", "3.0").is_none());
+        assert!(history.pending_for("foo", "2.0").is_some());
+    }
+}
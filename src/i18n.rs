@@ -0,0 +1,197 @@
+//! Message catalogs for bot replies, embedded from `locales/*.toml`.
+//!
+//! A catalog entry is either a string or a table of plural forms (`one`, `other`) picked by [`tn!`]. Placeholders
+//! are written as `{name}` and filled from the macro arguments. Entries missing from a catalog fall back to English.
+
+use std::{collections::HashMap, fmt, str::FromStr, sync::LazyLock};
+
+use eyre::eyre;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    En,
+    ZhCn,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::ZhCn];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::ZhCn => "zh-CN",
+        }
+    }
+
+    /// The name of the language in itself.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::ZhCn => "简体中文",
+        }
+    }
+
+    fn plural_category(&self, n: u64) -> &'static str {
+        match self {
+            Self::En if n == 1 => "one",
+            _ => "other",
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "en" | "en-us" | "en-gb" => Ok(Self::En),
+            "zh" | "zh-cn" | "zh-hans" => Ok(Self::ZhCn),
+            _ => Err(eyre!("Unknown language: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+type Catalog = HashMap<String, String>;
+
+static CATALOGS: LazyLock<HashMap<&'static str, Catalog>> = LazyLock::new(|| {
+    [
+        (Lang::En, include_str!("../locales/en.toml")),
+        (Lang::ZhCn, include_str!("../locales/zh-CN.toml")),
+    ]
+    .into_iter()
+    .map(|(lang, source)| {
+        let table = toml::from_str::<toml::Table>(source)
+            .unwrap_or_else(|e| panic!("Invalid {} catalog: {}", lang.code(), e));
+        let mut catalog = Catalog::new();
+        flatten(&mut catalog, "", table);
+        (lang.code(), catalog)
+    })
+    .collect()
+});
+
+fn flatten(catalog: &mut Catalog, prefix: &str, table: toml::Table) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            toml::Value::String(s) => {
+                catalog.insert(key, s);
+            }
+            toml::Value::Table(table) => flatten(catalog, &key, table),
+            other => {
+                catalog.insert(key, other.to_string());
+            }
+        }
+    }
+}
+
+fn lookup(lang: Lang, key: &str) -> Option<&'static str> {
+    [lang, Lang::En]
+        .iter()
+        .find_map(|lang| CATALOGS[lang.code()].get(key))
+        .map(|s| s.as_str())
+}
+
+fn fill(template: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut text = template.to_owned();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    text
+}
+
+/// Look up `key`, or return the key itself if no catalog has it.
+pub fn translate(lang: Lang, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    fill(lookup(lang, key).unwrap_or(key), args)
+}
+
+/// Look up the plural form of `key` for `count`, which is also available as the `{count}` placeholder.
+pub fn translate_plural(
+    lang: Lang,
+    key: &str,
+    count: u64,
+    args: &[(&str, &dyn fmt::Display)],
+) -> String {
+    let template = lookup(lang, &format!("{key}.{}", lang.plural_category(count)))
+        .or_else(|| lookup(lang, &format!("{key}.other")))
+        .unwrap_or(key);
+    let mut args = args.to_vec();
+    args.push(("count", &count));
+    fill(template, &args)
+}
+
+/// `t!(lang, "key", name = value, ...)`
+macro_rules! t {
+    ($lang:expr, $key:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::translate(
+            $lang,
+            $key,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),*],
+        )
+    };
+}
+
+/// `tn!(lang, "key", count, name = value, ...)`
+macro_rules! tn {
+    ($lang:expr, $key:expr, $count:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::translate_plural(
+            $lang,
+            $key,
+            $count as u64,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),*],
+        )
+    };
+}
+
+pub(crate) use {t, tn};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            t!(Lang::En, "reply.unknown_command", name = "foo"),
+            "Unknown command: foo. Type <code>!anic help</code> for available commands."
+        );
+        assert_eq!(t!(Lang::En, "no.such.key"), "no.such.key");
+        assert_eq!(tn!(Lang::En, "count.update", 1), "1 update");
+        assert_eq!(tn!(Lang::En, "count.update", 3), "3 updates");
+        assert_eq!(tn!(Lang::ZhCn, "count.update", 1), "1 个更新");
+        assert_eq!("zh_CN".parse::<Lang>().unwrap(), Lang::ZhCn);
+        assert!("fr".parse::<Lang>().is_err());
+    }
+
+    #[test]
+    fn test_catalogs() {
+        // Every Chinese entry should have an English counterpart and use the same placeholders.
+        let en = &CATALOGS[Lang::En.code()];
+        for (key, value) in &CATALOGS[Lang::ZhCn.code()] {
+            let Some(english) = en.get(key) else {
+                panic!("{key} is missing from the English catalog");
+            };
+            let placeholders = |s: &str| {
+                let mut names = s
+                    .split('{')
+                    .skip(1)
+                    .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_owned()))
+                    .collect::<Vec<String>>();
+                names.sort();
+                names.dedup();
+                names
+            };
+            assert_eq!(placeholders(value), placeholders(english), "{key}");
+        }
+    }
+}
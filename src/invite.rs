@@ -0,0 +1,170 @@
+//! Joining the rooms the bot is invited to, as the invite policy in the config says.
+//!
+//! Invites waiting for an admin to approve them only live in memory. The rooms are still invited after a restart, so
+//! the admins are asked about them again once the first sync is done.
+
+use matrix_sdk::{
+    Client, Room,
+    ruma::{OwnedRoomId, OwnedUserId, RoomId, UserId},
+};
+use std::{
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    bot::{dm_or_create, user_lang},
+    command::html_reply,
+    config::{Config, InviteMode, InvitePolicy},
+    db::DatabaseImpl,
+    i18n::t,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Join,
+    Reject,
+    /// Wait for an admin to approve the invite.
+    Ask,
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingInvite {
+    pub room_id: OwnedRoomId,
+    pub inviter: OwnedUserId,
+}
+
+static PENDING: LazyLock<Mutex<Vec<PendingInvite>>> = LazyLock::new(Default::default);
+
+/// Whether the bot is in as many rooms as the policy allows.
+pub fn is_full(policy: &InvitePolicy, joined: usize) -> bool {
+    policy.max_rooms > 0 && joined >= policy.max_rooms
+}
+
+/// What to do with an invite while the bot is in `joined` rooms.
+pub fn decide(policy: &InvitePolicy, is_direct: bool, joined: usize) -> Decision {
+    if is_full(policy, joined) {
+        return Decision::Reject;
+    }
+    match (policy.mode, is_direct) {
+        (_, true) | (InviteMode::Any, false) => Decision::Join,
+        (InviteMode::Direct, false) => Decision::Reject,
+        (InviteMode::Approval, false) => Decision::Ask,
+    }
+}
+
+pub fn pending() -> Vec<PendingInvite> {
+    PENDING.lock().unwrap().clone()
+}
+
+/// Stop waiting for an approval of the invite to `room_id`, returning it if there was one.
+pub fn take(room_id: &RoomId) -> Option<PendingInvite> {
+    let mut pending = PENDING.lock().unwrap();
+    let index = pending
+        .iter()
+        .position(|invite| invite.room_id == room_id)?;
+    Some(pending.remove(index))
+}
+
+/// Keep the invite of `inviter` to `room_id` until an admin approves or rejects it, and ask the admins to.
+pub fn ask_admins(
+    client: Client,
+    config: Config,
+    db: DatabaseImpl,
+    room_id: &RoomId,
+    inviter: &UserId,
+) {
+    {
+        let mut pending = PENDING.lock().unwrap();
+        if pending.iter().any(|invite| invite.room_id == room_id) {
+            return;
+        }
+        pending.push(PendingInvite {
+            room_id: room_id.to_owned(),
+            inviter: inviter.to_owned(),
+        });
+    }
+    let (room_id, inviter) = (room_id.to_owned(), inviter.to_owned());
+    tokio::spawn(async move {
+        for admin in config.admin_ids.iter() {
+            let lang = user_lang(&db, admin).await.unwrap_or_default();
+            let content = html_reply(t!(lang, "invite.request", user = inviter, room = room_id));
+            let sent = match dm_or_create(client.clone(), admin).await {
+                Ok(room) => room
+                    .send(content)
+                    .await
+                    .map(|_| ())
+                    .map_err(eyre::Report::from),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = sent {
+                warn!(
+                    "Unable to ask {} about the invite to {}: {}",
+                    admin, room_id, e
+                );
+            }
+        }
+    });
+}
+
+pub fn reject(room: Room) {
+    tokio::spawn(async move {
+        info!("Rejecting the invite to room {}.", room.room_id());
+        if let Err(err) = room.leave().await {
+            error!(
+                "Failed to reject the invite to room {}: {:?}",
+                room.room_id(),
+                err
+            );
+        }
+    });
+}
+
+pub fn join(room: Room) {
+    tokio::spawn(async move {
+        for retry in 0.. {
+            info!("Joining room {}.", room.room_id());
+            match room.join().await {
+                Ok(_) => {
+                    info!("Joined room {}.", room.room_id());
+                    return;
+                }
+                Err(err) => {
+                    // https://github.com/matrix-org/synapse/issues/4345
+                    if retry >= 16 {
+                        error!("Failed to join room {}: {:?}", room.room_id(), err);
+                        error!("Too many retries, giving up after 1 hour.");
+                        return;
+                    } else {
+                        const BASE: f64 = 1.6180339887498947;
+                        let duration = BASE.powi(retry);
+                        warn!("Failed to join room {}: {:?}", room.room_id(), err);
+                        warn!("This is common, will retry in {:.1}s.", duration);
+                        tokio::time::sleep(Duration::from_secs_f64(duration)).await;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decide() {
+        let mut policy = InvitePolicy::default();
+        assert_eq!(decide(&policy, true, 10), Decision::Join);
+        assert_eq!(decide(&policy, false, 10), Decision::Reject);
+        policy.mode = InviteMode::Approval;
+        assert_eq!(decide(&policy, true, 10), Decision::Join);
+        assert_eq!(decide(&policy, false, 10), Decision::Ask);
+        policy.mode = InviteMode::Any;
+        assert_eq!(decide(&policy, false, 10), Decision::Join);
+        policy.max_rooms = 10;
+        assert_eq!(decide(&policy, true, 9), Decision::Join);
+        assert_eq!(decide(&policy, true, 10), Decision::Reject);
+    }
+}
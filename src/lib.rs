@@ -1 +1,7 @@
 pub mod anicca;
+pub mod changelog;
+pub mod duration;
+pub mod filter;
+pub mod history;
+pub mod search;
+pub mod version;
@@ -1,5 +1,5 @@
 use clap::Parser;
-use command::parse_prefix_and_args;
+use command::{strip_command_prefix, strip_reply_fallback};
 use eyre::{Result, eyre};
 use matrix_sdk::{
     Client, Room, RoomState,
@@ -7,40 +7,53 @@ use matrix_sdk::{
     event_handler::Ctx,
     room::Receipts,
     ruma::{
-        OwnedEventId,
+        OwnedEventId, UserId,
         api::client::filter::FilterDefinition,
         events::{
+            reaction::OriginalSyncReactionEvent,
             relation::{InReplyTo, Thread},
             room::{
                 encrypted::SyncRoomEncryptedEvent,
                 member::{MembershipState, StrippedRoomMemberEvent, SyncRoomMemberEvent},
-                message::{MessageType, OriginalSyncRoomMessageEvent, Relation},
+                message::{
+                    MessageType, OriginalSyncRoomMessageEvent, Relation, RoomMessageEventContent,
+                },
             },
         },
     },
 };
 use std::{
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 use tracing::{debug, error, info, instrument, warn};
 use tracing_subscriber::{EnvFilter, prelude::*};
 
+mod access;
 mod bot;
 mod cli;
 mod command;
 mod config;
 mod db;
+mod error;
+mod i18n;
+mod invite;
+mod ratelimit;
 
 use cli::{Cli, Subcommands};
 use config::Config;
 use db::*;
+use error::error_reply;
+use invite::Decision;
+use ratelimit::RateLimiter;
 
 #[derive(Clone)]
 struct Payload {
     db: DatabaseImpl,
     data_dir: PathBuf,
     config: Config,
+    limiter: Arc<RateLimiter>,
 }
 
 #[tokio::main]
@@ -95,6 +108,8 @@ async fn run(data_dir: &Path, config: Config) -> Result<()> {
     let (client, sync_helper) = matrixbot_ezlogin::login(data_dir).await?;
 
     let database = DatabaseImpl::new(data_dir)?;
+    // Create tables introduced after the initial setup.
+    database.init().await?;
     let notify_client = client.clone();
     let data_dir_owned = data_dir.to_path_buf();
     let notify_db = database.clone();
@@ -114,6 +129,17 @@ async fn run(data_dir: &Path, config: Config) -> Result<()> {
         }
     });
 
+    let context = Payload {
+        db: database.clone(),
+        data_dir: data_dir.to_owned(),
+        limiter: Arc::new(RateLimiter::new(
+            config.rate_limit.clone(),
+            anicca_subscribe::duration::now(),
+        )),
+        config,
+    };
+    client.add_event_handler_context(context.clone());
+
     // We don't ignore joining and leaving events happened during downtime.
     client.add_event_handler(on_invite);
     client.add_event_handler(on_leave);
@@ -130,12 +156,39 @@ async fn run(data_dir: &Path, config: Config) -> Result<()> {
         .sync_once(&client, sync_settings.clone())
         .await?;
 
-    client.add_event_handler_context(Payload {
-        db: database.clone(),
-        data_dir: data_dir.to_owned(),
-        config,
+    // Invites waiting for approval are only kept in memory, so ask the admins about them again.
+    for room in client.invited_rooms() {
+        if invite::pending()
+            .iter()
+            .any(|item| item.room_id == room.room_id())
+        {
+            continue;
+        }
+        let inviter = match room.invite_details().await {
+            Ok(details) => details.inviter,
+            Err(e) => {
+                warn!("Unable to get the invite to room {}: {}", room.room_id(), e);
+                continue;
+            }
+        };
+        let Some(inviter) = inviter else {
+            continue;
+        };
+        let is_direct = room.is_direct().await.unwrap_or(false);
+        consider_invite(&client, &context, room, inviter.user_id(), is_direct).await;
+    }
+
+    // Direct chats are known after the first sync.
+    let announce_client = client.clone();
+    let announce_db = database.clone();
+    tokio::spawn(async move {
+        if let Err(e) = bot::announce_version(announce_client, announce_db).await {
+            warn!("Unable to announce the new version: {}", e);
+        }
     });
+
     client.add_event_handler(on_message);
+    client.add_event_handler(on_reaction);
     client.add_event_handler(on_utd);
 
     // Forget rooms that we already left
@@ -177,6 +230,12 @@ fn set_read_marker(room: Room, event_id: OwnedEventId) {
     });
 }
 
+/// Whether `user_id` may run a command now. `Err` holds what to reply instead, if anything.
+async fn admit(context: &Payload, user_id: &UserId) -> Result<(), Option<RoomMessageEventContent>> {
+    access::check_command(&context.config, &context.db, user_id).await?;
+    ratelimit::check_command(&context.limiter, &context.config, &context.db, user_id).await
+}
+
 // https://spec.matrix.org/v1.14/client-server-api/#mroommessage
 #[instrument(skip_all)]
 async fn on_message(
@@ -205,40 +264,96 @@ async fn on_message(
         );
         return Ok(());
     }
-    if !matches!(event.content.msgtype, MessageType::Text(_)) {
-        info!(
-            "Ignoring event {}: Message type is {}.",
-            event.event_id,
-            event.content.msgtype()
-        );
-        return Ok(());
-    }
-
-    let MessageType::Text(ref text) = event.content.msgtype else {
-        unreachable!()
-    };
-
     let is_direct = room.is_direct().await?;
-    let display_name = client
-        .account()
-        .get_display_name()
-        .await?
-        .unwrap_or("anicca".to_owned());
-    let parsed_args = parse_prefix_and_args(is_direct, &text.body, client.user_id(), &display_name);
-    let mut reply = if let Some(args) = parsed_args {
-        set_read_marker(room.clone(), event.event_id.clone());
-        command::handle(
-            context.config.clone(),
-            &context.data_dir,
-            context.db.clone(),
-            &event.sender,
-            room.clone(),
-            &args,
-        )
-        .await?
-    } else {
-        debug!("Ignoring: Not command: {:?}.", text);
-        return Ok(());
+    let mut reply = match event.content.msgtype {
+        MessageType::Text(ref text) => {
+            let display_name = client
+                .account()
+                .get_display_name()
+                .await?
+                .unwrap_or("anicca".to_owned());
+            // A command replying to a notification acts on the packages of that notification.
+            let reply_to = match &event.content.relates_to {
+                Some(Relation::Reply { in_reply_to }) => Some(&*in_reply_to.event_id),
+                Some(Relation::Thread(thread)) => {
+                    thread.in_reply_to.as_ref().map(|reply| &*reply.event_id)
+                }
+                _ => None,
+            };
+            let body = match reply_to {
+                Some(_) => strip_reply_fallback(&text.body),
+                None => &text.body,
+            };
+            let Some(command) =
+                strip_command_prefix(is_direct, body, client.user_id(), &display_name)
+            else {
+                debug!("Ignoring: Not command: {:?}.", text);
+                return Ok(());
+            };
+            set_read_marker(room.clone(), event.event_id.clone());
+            match admit(&context, &event.sender).await {
+                Ok(()) => match command::handle(
+                    context.config.clone(),
+                    &context.data_dir,
+                    context.db.clone(),
+                    &event.sender,
+                    room.clone(),
+                    command,
+                    reply_to,
+                )
+                .await
+                {
+                    Ok(reply) => reply,
+                    Err(e) => {
+                        error_reply(
+                            &client,
+                            &context.config,
+                            &context.db,
+                            &event.sender,
+                            command,
+                            e,
+                        )
+                        .await
+                    }
+                },
+                Err(Some(reply)) => reply,
+                Err(None) => return Ok(()),
+            }
+        }
+        // Files sent in direct messages are subscription lists to import.
+        MessageType::File(ref file) if is_direct => {
+            set_read_marker(room.clone(), event.event_id.clone());
+            match admit(&context, &event.sender).await {
+                Ok(()) => {
+                    match command::handle_file(&client, context.db.clone(), &event.sender, file)
+                        .await
+                    {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            error_reply(
+                                &client,
+                                &context.config,
+                                &context.db,
+                                &event.sender,
+                                file.filename(),
+                                e,
+                            )
+                            .await
+                        }
+                    }
+                }
+                Err(Some(reply)) => reply,
+                Err(None) => return Ok(()),
+            }
+        }
+        _ => {
+            info!(
+                "Ignoring event {}: Message type is {}.",
+                event.event_id,
+                event.content.msgtype()
+            );
+            return Ok(());
+        }
     };
 
     // We should use make_reply_to, but it embeds the original message body, which I don't want
@@ -253,10 +368,15 @@ async fn on_message(
         }),
     };
 
+    let body = reply.body().to_owned();
     tokio::spawn(async move {
         info!("Sending a reply message to {}.", event.event_id);
         match room.send(reply).await {
-            Ok(_) => info!("Sent a reply message to {}.", event.event_id),
+            Ok(sent) => {
+                info!("Sent a reply message to {}.", event.event_id);
+                // Reacting to a preview confirms its action.
+                command::confirm::attach_preview(&event.sender, &body, &sent.response.event_id);
+            }
             Err(err) => error!(
                 "Failed to send a reply message to {}: {:?}",
                 event.event_id, err
@@ -267,6 +387,67 @@ async fn on_message(
     Ok(())
 }
 
+// https://spec.matrix.org/v1.14/client-server-api/#mreaction
+#[instrument(skip_all)]
+async fn on_reaction(
+    event: OriginalSyncReactionEvent,
+    room: Room,
+    client: Client,
+    context: Ctx<Payload>,
+) -> Result<()> {
+    if event.sender == client.user_id().unwrap() {
+        // Ignore my own reaction
+        return Ok(());
+    }
+    debug!("room = {}, event = {:?}", room.room_id(), event);
+    let annotation = event.content.relates_to;
+    if !command::is_confirmation(&event.sender, &annotation.event_id, &annotation.key) {
+        return Ok(());
+    }
+    let mut reply = match admit(&context, &event.sender).await {
+        Ok(()) => match command::handle_reaction(
+            &client,
+            context.db.clone(),
+            &event.sender,
+            &annotation.event_id,
+            &annotation.key,
+        )
+        .await
+        {
+            Ok(Some(reply)) => reply,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                error_reply(
+                    &client,
+                    &context.config,
+                    &context.db,
+                    &event.sender,
+                    &annotation.key,
+                    e,
+                )
+                .await
+            }
+        },
+        Err(Some(reply)) => reply,
+        Err(None) => return Ok(()),
+    };
+    reply.relates_to = Some(Relation::Reply {
+        in_reply_to: InReplyTo::new(annotation.event_id.clone()),
+    });
+
+    tokio::spawn(async move {
+        info!("Sending a reply to reaction {}.", event.event_id);
+        if let Err(err) = room.send(reply).await {
+            error!(
+                "Failed to send a reply to reaction {}: {:?}",
+                event.event_id, err
+            );
+        }
+    });
+
+    Ok(())
+}
+
 // The SDK documentation said nothing about how to catch unable-to-decrypt (UTD) events.
 // But it seems this handler can capture them.
 //
@@ -277,12 +458,17 @@ async fn on_utd(event: SyncRoomEncryptedEvent, room: Room) {
     error!("Unable to decrypt message {}.", event.event_id());
 }
 
-// Whenever someone invites me to a room, join if it is a direct chat.
+// Whenever someone invites me to a room, join it if the invite policy allows, or ask the admins.
 //
 // https://spec.matrix.org/v1.14/client-server-api/#mroommember
 // https://spec.matrix.org/v1.14/client-server-api/#stripped-state
 #[instrument(skip_all)]
-async fn on_invite(event: StrippedRoomMemberEvent, room: Room, client: Client) {
+async fn on_invite(
+    event: StrippedRoomMemberEvent,
+    room: Room,
+    client: Client,
+    context: Ctx<Payload>,
+) {
     let user_id = client.user_id().unwrap();
     if event.sender == user_id {
         return;
@@ -304,32 +490,52 @@ async fn on_invite(event: StrippedRoomMemberEvent, room: Room, client: Client) {
         );
         return;
     }
+    let is_direct = event.content.is_direct.unwrap_or(false);
+    consider_invite(&client, &context, room, &event.sender, is_direct).await;
+}
 
-    tokio::spawn(async move {
-        for retry in 0.. {
-            info!("Joining room {}.", room.room_id());
-            match room.join().await {
-                Ok(_) => {
-                    info!("Joined room {}.", room.room_id());
-                    return;
-                }
-                Err(err) => {
-                    // https://github.com/matrix-org/synapse/issues/4345
-                    if retry >= 16 {
-                        error!("Failed to join room {}: {:?}", room.room_id(), err);
-                        error!("Too many retries, giving up after 1 hour.");
-                        return;
-                    } else {
-                        const BASE: f64 = 1.6180339887498947;
-                        let duration = BASE.powi(retry);
-                        warn!("Failed to join room {}: {:?}", room.room_id(), err);
-                        warn!("This is common, will retry in {:.1}s.", duration);
-                        tokio::time::sleep(Duration::from_secs_f64(duration)).await;
-                    }
-                }
-            }
+/// Join the room `inviter` invited me to, reject the invite or ask the admins, as the access and invite policies say.
+async fn consider_invite(
+    client: &Client,
+    context: &Payload,
+    room: Room,
+    inviter: &UserId,
+    is_direct: bool,
+) {
+    if !access::check(&context.config, &context.db, inviter).await {
+        info!(
+            "Rejecting room {}: {} may not use the bot.",
+            room.room_id(),
+            inviter
+        );
+        invite::reject(room);
+        return;
+    }
+
+    match invite::decide(
+        &context.config.invites,
+        is_direct,
+        client.joined_rooms().len(),
+    ) {
+        Decision::Join => invite::join(room),
+        Decision::Reject => {
+            info!(
+                "Rejecting room {}: The invite policy does not allow joining it.",
+                room.room_id()
+            );
+            invite::reject(room);
         }
-    });
+        Decision::Ask => {
+            info!("Asking the admins whether to join room {}.", room.room_id());
+            invite::ask_admins(
+                client.clone(),
+                context.config.clone(),
+                context.db.clone(),
+                room.room_id(),
+                inviter,
+            );
+        }
+    }
 }
 
 // Whenever someone leaves a room, check whether I am the last remaining member.
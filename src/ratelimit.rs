@@ -0,0 +1,226 @@
+//! Token buckets limiting how many commands each user, and all users together, can send.
+//!
+//! Users who keep sending commands over their limit are blocked for a while. All of this only lives in memory, so a
+//! restart forgives everyone.
+
+use anicca_subscribe::duration;
+use matrix_sdk::ruma::{OwnedUserId, UserId, events::room::message::RoomMessageEventContent};
+use std::{collections::HashMap, mem, sync::Mutex, time::Duration};
+use tracing::warn;
+
+use crate::{
+    bot::user_lang,
+    config::{Config, RateLimit},
+    db::DatabaseImpl,
+    i18n::t,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    /// Over the limit, with the seconds until the next command is allowed.
+    Limited(u64),
+    /// Blocked from now on until the given time.
+    Blocked(u64),
+    /// Over the limit and already told so, or blocked.
+    Ignore,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: u64,
+}
+
+impl Bucket {
+    fn full(burst: u32, now: u64) -> Self {
+        Self {
+            tokens: burst.into(),
+            updated: now,
+        }
+    }
+
+    /// Take a token after refilling the ones regained since the last time, or return the seconds until there is one.
+    fn take(&mut self, burst: u32, per_minute: u32, now: u64) -> Result<(), u64> {
+        if per_minute == 0 {
+            return Ok(());
+        }
+        let regained = now.saturating_sub(self.updated) as f64 * f64::from(per_minute) / 60.0;
+        self.tokens = (self.tokens + regained).min(burst.into());
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - self.tokens) * 60.0 / f64::from(per_minute)).ceil() as u64)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UserState {
+    bucket: Bucket,
+    /// Commands over the limit, forgotten once none came for as long as a block lasts.
+    strikes: u32,
+    last_strike: u64,
+    /// Whether the user was told about being over the limit since their last allowed command.
+    warned: bool,
+    blocked_until: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    global: Bucket,
+    users: HashMap<OwnedUserId, UserState>,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimit,
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimit, now: u64) -> Self {
+        Self {
+            state: Mutex::new(State {
+                global: Bucket::full(config.global_burst, now),
+                users: HashMap::new(),
+            }),
+            config,
+        }
+    }
+
+    /// Count a command of `user_id` sent at `now` against the limits.
+    pub fn check(&self, user_id: &UserId, now: u64) -> Verdict {
+        let config = &self.config;
+        let block_secs = config.block_minutes * 60;
+        let mut state = self.state.lock().unwrap();
+        let State { global, users } = &mut *state;
+        // Users who have not been around for a while start over with a full bucket.
+        users.retain(|_, user| {
+            user.blocked_until > now || now.saturating_sub(user.bucket.updated) < block_secs
+        });
+        let user = users
+            .entry(user_id.to_owned())
+            .or_insert_with(|| UserState {
+                bucket: Bucket::full(config.user_burst, now),
+                strikes: 0,
+                last_strike: now,
+                warned: false,
+                blocked_until: 0,
+            });
+        if user.blocked_until > now {
+            return Verdict::Ignore;
+        }
+        if let Err(retry_after) = user
+            .bucket
+            .take(config.user_burst, config.user_per_minute, now)
+        {
+            if now.saturating_sub(user.last_strike) >= block_secs {
+                user.strikes = 0;
+            }
+            user.strikes += 1;
+            user.last_strike = now;
+            if config.strikes > 0 && user.strikes >= config.strikes {
+                user.strikes = 0;
+                user.blocked_until = now + block_secs;
+                return Verdict::Blocked(user.blocked_until);
+            }
+            if mem::replace(&mut user.warned, true) {
+                return Verdict::Ignore;
+            }
+            return Verdict::Limited(retry_after);
+        }
+        match global.take(config.global_burst, config.global_per_minute, now) {
+            Ok(()) => {
+                user.warned = false;
+                Verdict::Allow
+            }
+            Err(_) if mem::replace(&mut user.warned, true) => Verdict::Ignore,
+            Err(retry_after) => Verdict::Limited(retry_after),
+        }
+    }
+}
+
+/// Count a command of `user_id` against the limits. `Err` holds what to reply instead of running the command, if
+/// anything.
+pub async fn check_command(
+    limiter: &RateLimiter,
+    config: &Config,
+    db: &DatabaseImpl,
+    user_id: &UserId,
+) -> Result<(), Option<RoomMessageEventContent>> {
+    if config.is_admin(user_id) {
+        return Ok(());
+    }
+    let now = duration::now();
+    let verdict = limiter.check(user_id, now);
+    let lang = match verdict {
+        Verdict::Allow => return Ok(()),
+        Verdict::Ignore => return Err(None),
+        _ => user_lang(db, user_id).await.unwrap_or_default(),
+    };
+    Err(Some(RoomMessageEventContent::notice_plain(match verdict {
+        Verdict::Limited(secs) => t!(
+            lang,
+            "ratelimit.cooldown",
+            duration = duration::format(Duration::from_secs(secs))
+        ),
+        Verdict::Blocked(until) => {
+            warn!("Blocking {} for sending too many commands.", user_id);
+            t!(
+                lang,
+                "ratelimit.blocked",
+                duration = duration::format(Duration::from_secs(until - now))
+            )
+        }
+        Verdict::Allow | Verdict::Ignore => unreachable!(),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let limiter = RateLimiter::new(
+            RateLimit {
+                user_burst: 2,
+                user_per_minute: 60,
+                global_burst: 3,
+                global_per_minute: 0,
+                strikes: 3,
+                block_minutes: 1,
+            },
+            0,
+        );
+        let user = UserId::parse("@user:example.org").unwrap();
+        assert_eq!(limiter.check(&user, 0), Verdict::Allow);
+        assert_eq!(limiter.check(&user, 0), Verdict::Allow);
+        assert_eq!(limiter.check(&user, 0), Verdict::Limited(1));
+        assert_eq!(limiter.check(&user, 0), Verdict::Ignore);
+        assert_eq!(limiter.check(&user, 1), Verdict::Allow);
+        assert_eq!(limiter.check(&user, 1), Verdict::Blocked(61));
+        assert_eq!(limiter.check(&user, 30), Verdict::Ignore);
+        assert_eq!(limiter.check(&user, 61), Verdict::Allow);
+
+        let limiter = RateLimiter::new(
+            RateLimit {
+                user_burst: 5,
+                user_per_minute: 0,
+                global_burst: 1,
+                global_per_minute: 30,
+                strikes: 0,
+                block_minutes: 1,
+            },
+            0,
+        );
+        let other = UserId::parse("@other:example.org").unwrap();
+        assert_eq!(limiter.check(&user, 0), Verdict::Allow);
+        assert_eq!(limiter.check(&other, 0), Verdict::Limited(2));
+        assert_eq!(limiter.check(&other, 1), Verdict::Ignore);
+        assert_eq!(limiter.check(&other, 2), Verdict::Allow);
+    }
+}
@@ -0,0 +1,222 @@
+use std::cmp::Ordering;
+
+/// The longest query matched fuzzily. Longer ones would only make edit distances slow to compute.
+pub const MAX_QUERY_LEN: usize = 64;
+
+/// How well a package matches a search query. Better matches sort first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    Exact,
+    Prefix,
+    Substring,
+    /// The query only appears in the package path, e.g. its section.
+    Path,
+    /// The query is close to the name, ranked by edit distance.
+    Fuzzy(usize),
+}
+
+/// Edit distance between two strings counting insertions, deletions, substitutions and swaps of adjacent
+/// characters, which covers most typos in package names.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<char>>();
+    let b = b.chars().collect::<Vec<char>>();
+    // Only the last two rows of the table are needed, the one before them for swaps.
+    let mut before = vec![0; b.len() + 1];
+    let mut last = (0..=b.len()).collect::<Vec<usize>>();
+    let mut row = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        row[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        (before, last, row) = (last, row, before);
+    }
+    last[b.len()]
+}
+
+/// The largest edit distance still considered a typo of `query`.
+pub fn max_typos(query: &str) -> usize {
+    (query.chars().count() / 4).clamp(1, 3)
+}
+
+/// The closest of `candidates` to a misspelled `name`, at most `limit` of them.
+pub fn suggest<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<&'a str> {
+    let name = name.to_lowercase();
+    let name_len = name.chars().count();
+    if name_len > MAX_QUERY_LEN {
+        return Vec::new();
+    }
+    let typos = max_typos(&name);
+    let mut suggestions = candidates
+        .into_iter()
+        .filter(|candidate| candidate.chars().count().abs_diff(name_len) <= typos)
+        .filter_map(|candidate| {
+            let distance = edit_distance(&name, &candidate.to_lowercase());
+            (distance <= typos).then_some((MatchKind::Fuzzy(distance), candidate))
+        })
+        .collect::<Vec<_>>();
+    suggestions.sort_by(|a, b| compare(*a, *b));
+    suggestions.dedup_by_key(|(_, candidate)| *candidate);
+    suggestions
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Whether all characters of `query` appear in `text` in order.
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+pub fn rank(query: &str, name: &str, path: &str) -> Option<MatchKind> {
+    let query = query.to_lowercase();
+    let name = name.to_lowercase();
+    if name == query {
+        Some(MatchKind::Exact)
+    } else if name.starts_with(&query) {
+        Some(MatchKind::Prefix)
+    } else if name.contains(&query) {
+        Some(MatchKind::Substring)
+    } else if path.to_lowercase().contains(&query) {
+        Some(MatchKind::Path)
+    } else {
+        let query_len = query.chars().count();
+        let name_len = name.chars().count();
+        let typos = max_typos(&query);
+        if query_len > MAX_QUERY_LEN {
+            return None;
+        }
+        // The distance is at least the difference in length, so there is no need to compute it for names far longer
+        // or shorter than the query.
+        if query_len.abs_diff(name_len) <= typos {
+            let distance = edit_distance(&query, &name);
+            if distance <= typos {
+                return Some(MatchKind::Fuzzy(distance));
+            }
+        }
+        if query_len >= 3 && is_subsequence(&query, &name) {
+            // Abbreviations like `qtwe` for `qt-webengine` rank after every typo, by the characters they leave out.
+            Some(MatchKind::Fuzzy(typos + name_len - query_len))
+        } else {
+            None
+        }
+    }
+}
+
+/// Order two ranked matches, breaking ties by shorter and then alphabetically smaller names.
+pub fn compare(a: (MatchKind, &str), b: (MatchKind, &str)) -> Ordering {
+    a.0.cmp(&b.0)
+        .then_with(|| a.1.len().cmp(&b.1.len()))
+        .then_with(|| a.1.cmp(b.1))
+}
+
+/// Whether `name` matches the shell-style `pattern`, where `*` stands for any text and `?` for any one character.
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+    let (mut p, mut n) = (0, 0);
+    // The last `*` seen and the position in `name` it currently stops at, to backtrack to on a mismatch.
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("llvm", "llvm"), 0);
+        assert_eq!(edit_distance("", "gcc"), 3);
+        assert_eq!(edit_distance("firefxo", "firefox"), 1);
+        assert_eq!(edit_distance("ca", "abc"), 3);
+    }
+
+    #[test]
+    fn test_rank() {
+        let path = "lang-rust/rust";
+        assert_eq!(rank("rust", "rust", path), Some(MatchKind::Exact));
+        assert_eq!(rank("Rust", "rust-bindgen", path), Some(MatchKind::Prefix));
+        assert_eq!(
+            rank("bind", "rust-bindgen", path),
+            Some(MatchKind::Substring)
+        );
+        assert_eq!(
+            rank("lang-", "cargo-c", "lang-rust/cargo-c"),
+            Some(MatchKind::Path)
+        );
+        assert_eq!(rank("rsut", "rust", path), Some(MatchKind::Fuzzy(1)));
+        assert_eq!(
+            rank("qtwe", "qt-webengine", "runtime-kde/qt-webengine"),
+            Some(MatchKind::Fuzzy(9))
+        );
+        assert_eq!(rank("gcc", "rust", path), None);
+        assert_eq!(rank(&"rsut".repeat(100), "rust", path), None);
+
+        let mut names = vec![
+            (MatchKind::Substring, "rust-bindgen"),
+            (MatchKind::Prefix, "rustup"),
+            (MatchKind::Prefix, "rust-std"),
+            (MatchKind::Exact, "rust"),
+        ];
+        names.sort_by(|a, b| compare(*a, *b));
+        assert_eq!(
+            names.iter().map(|(_, name)| *name).collect::<Vec<&str>>(),
+            ["rust", "rustup", "rust-std", "rust-bindgen"]
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        let names = ["firefox", "firefox-esr", "thunderbird", "fireflies"];
+        assert_eq!(suggest("firefxo", names, 3), ["firefox"]);
+        assert_eq!(suggest("Firefox-es", names, 3), ["firefox-esr"]);
+        assert_eq!(suggest("llvm", names, 3), Vec::<&str>::new());
+        assert_eq!(suggest("firefo", names, 1), ["firefox"]);
+        assert_eq!(suggest(&"firefox".repeat(20), names, 3), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("python-*", "python-requests"));
+        assert!(glob_matches("*-git", "neovim-git"));
+        assert!(glob_matches("lib?", "libx"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("python-*", "python3"));
+        assert!(!glob_matches("lib?", "libxy"));
+        assert!(!glob_matches("a*b", "aXbY"));
+        assert!(!glob_matches("rust", "rustup"));
+    }
+}
@@ -0,0 +1,346 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use eyre::{Result, bail, eyre};
+
+const PRERELEASE_TAGS: [&str; 8] = [
+    "alpha", "beta", "rc", "pre", "preview", "dev", "snapshot", "nightly",
+];
+
+/// A loosely parsed package version, good enough for comparing anicca `before`/`after` fields.
+#[derive(Debug, Clone)]
+pub struct Version {
+    epoch: u64,
+    release: Vec<u64>,
+    prerelease: bool,
+}
+
+impl Version {
+    pub fn parse(text: &str) -> Self {
+        let (epoch, rest) = match text.split_once(':') {
+            Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => {
+                (epoch.parse().unwrap_or_default(), rest)
+            }
+            _ => (0, text),
+        };
+
+        let mut release = Vec::new();
+        let mut prerelease = false;
+        let mut in_release = true;
+        for (is_digit, segment) in segments(rest) {
+            if is_digit {
+                if in_release {
+                    release.push(segment.parse().unwrap_or(u64::MAX));
+                }
+            } else {
+                in_release = false;
+                let segment = segment.to_ascii_lowercase();
+                if PRERELEASE_TAGS.contains(&segment.as_str()) {
+                    prerelease = true;
+                }
+            }
+        }
+        // Python style pre-releases such as `3.14.0a2` or `2.0b1`, where the letter directly follows
+        // the release numbers. Later letters, like in `1.0+git20240501.1a2b3c`, are not looked at.
+        let tail = rest.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        if rest[..rest.len() - tail.len()].ends_with(|c: char| c.is_ascii_digit())
+            && let Some(after) = tail.strip_prefix(['a', 'b'])
+            && after.starts_with(|c: char| c.is_ascii_digit())
+        {
+            prerelease = true;
+        }
+
+        Self {
+            epoch,
+            release,
+            prerelease,
+        }
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        self.prerelease
+    }
+
+    /// The most significant component that differs between `self` and `newer`.
+    pub fn bump_to(&self, newer: &Self) -> BumpKind {
+        if self.epoch != newer.epoch {
+            return BumpKind::Major;
+        }
+        let len = self.release.len().max(newer.release.len());
+        for i in 0..len {
+            let old = self.release.get(i).copied().unwrap_or_default();
+            let new = newer.release.get(i).copied().unwrap_or_default();
+            if old != new {
+                return match i {
+                    0 => BumpKind::Major,
+                    1 => BumpKind::Minor,
+                    _ => BumpKind::Patch,
+                };
+            }
+        }
+        BumpKind::Patch
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch.cmp(&other.epoch).then_with(|| {
+            let len = self.release.len().max(other.release.len());
+            (0..len)
+                .map(|i| {
+                    let a = self.release.get(i).copied().unwrap_or_default();
+                    let b = other.release.get(i).copied().unwrap_or_default();
+                    a.cmp(&b)
+                })
+                .find(|ord| ord.is_ne())
+                .unwrap_or(Ordering::Equal)
+                // A pre-release sorts before the release it leads to.
+                .then_with(|| other.prerelease.cmp(&self.prerelease))
+        })
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Split a version string into alternating runs of digits and letters, dropping separators.
+fn segments(text: &str) -> Vec<(bool, &str)> {
+    let mut segments = Vec::new();
+    let mut start = None;
+    let mut is_digit = false;
+    for (i, c) in text.char_indices() {
+        let kind = if c.is_ascii_digit() {
+            Some(true)
+        } else if c.is_alphabetic() {
+            Some(false)
+        } else {
+            None
+        };
+        match (start, kind) {
+            (Some(s), Some(k)) if k != is_digit => {
+                segments.push((is_digit, &text[s..i]));
+                start = Some(i);
+                is_digit = k;
+            }
+            (Some(s), None) => {
+                segments.push((is_digit, &text[s..i]));
+                start = None;
+            }
+            (None, Some(k)) => {
+                start = Some(i);
+                is_digit = k;
+            }
+            _ => (),
+        }
+    }
+    if let Some(s) = start {
+        segments.push((is_digit, &text[s..]));
+    }
+    segments
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BumpKind {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl FromStr for BumpKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "patch" => Ok(Self::Patch),
+            "minor" => Ok(Self::Minor),
+            "major" => Ok(Self::Major),
+            _ => Err(eyre!("Unknown bump kind: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for BumpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Patch => "patch",
+            Self::Minor => "minor",
+            Self::Major => "major",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    pub fn test(&self, ord: Ordering) -> bool {
+        match self {
+            Self::Eq => ord.is_eq(),
+            Self::Ne => ord.is_ne(),
+            Self::Gt => ord.is_gt(),
+            Self::Ge => ord.is_ge(),
+            Self::Lt => ord.is_lt(),
+            Self::Le => ord.is_le(),
+        }
+    }
+
+    /// Split a leading comparison operator off `text`.
+    pub fn split(text: &str) -> Option<(Self, &str)> {
+        [
+            (">=", Self::Ge),
+            ("<=", Self::Le),
+            ("==", Self::Eq),
+            ("!=", Self::Ne),
+            (">", Self::Gt),
+            ("<", Self::Lt),
+            ("=", Self::Eq),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| text.strip_prefix(token).map(|rest| (op, rest)))
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+        })
+    }
+}
+
+/// A condition on a pending update, written right after the package name when subscribing,
+/// e.g. `llvm>=20` or `openssl@major`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// Compare the `after` version against a fixed version.
+    Version(Op, String),
+    /// Require the update to be at least this kind of bump.
+    Bump(BumpKind),
+}
+
+impl Constraint {
+    pub fn matches(&self, before: &str, after: &str) -> bool {
+        match self {
+            Self::Version(op, version) => {
+                op.test(Version::parse(after).cmp(&Version::parse(version)))
+            }
+            Self::Bump(kind) => Version::parse(before).bump_to(&Version::parse(after)) >= *kind,
+        }
+    }
+
+    /// Split a subscription argument like `llvm>=20` into the package name and its constraint.
+    pub fn split_spec(spec: &str) -> Result<(&str, Option<Self>)> {
+        match spec.find(['<', '>', '=', '!', '@']) {
+            Some(0) => bail!("Missing package name in {spec}"),
+            Some(i) => Ok((&spec[..i], Some(spec[i..].parse()?))),
+            None => Ok((spec, None)),
+        }
+    }
+}
+
+impl FromStr for Constraint {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(kind) = s.strip_prefix('@') {
+            return Ok(Self::Bump(kind.parse()?));
+        }
+        match Op::split(s) {
+            Some((_, "")) | None => Err(eyre!("Invalid version constraint: {s}")),
+            Some((op, version)) => Ok(Self::Version(op, version.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Version(op, version) => write!(f, "{op}{version}"),
+            Self::Bump(kind) => write!(f, "@{kind}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        assert!(Version::parse("20.1.0") > Version::parse("19.1.7"));
+        assert!(Version::parse("20.1.0") >= Version::parse("20"));
+        assert!(Version::parse("1:0.1") > Version::parse("9.9"));
+        assert!(Version::parse("2.0.0-rc1") < Version::parse("2.0.0"));
+        assert_eq!(Version::parse("3.0"), Version::parse("3.0.0"));
+    }
+
+    #[test]
+    fn test_prerelease() {
+        assert!(Version::parse("6.15-rc3").is_prerelease());
+        assert!(Version::parse("3.14.0a2").is_prerelease());
+        assert!(Version::parse("2.0.0-beta.1").is_prerelease());
+        assert!(!Version::parse("0.9.8zh").is_prerelease());
+        assert!(!Version::parse("1.0+git20240501.1a2b3c").is_prerelease());
+        assert!(!Version::parse("2.1~git20240501.0b4e2").is_prerelease());
+        assert!(!Version::parse("1.2.3").is_prerelease());
+    }
+
+    #[test]
+    fn test_bump() {
+        let bump = |a: &str, b: &str| Version::parse(a).bump_to(&Version::parse(b));
+        assert_eq!(bump("3.4.1", "4.0.0"), BumpKind::Major);
+        assert_eq!(bump("3.4.1", "3.5"), BumpKind::Minor);
+        assert_eq!(bump("3.4.1", "3.4.2"), BumpKind::Patch);
+        assert_eq!(bump("3.4.1", "3.4.1a"), BumpKind::Patch);
+    }
+
+    #[test]
+    fn test_constraint() -> Result<()> {
+        assert_eq!(
+            Constraint::split_spec("llvm>=20")?,
+            ("llvm", Some(Constraint::Version(Op::Ge, "20".to_owned())))
+        );
+        assert_eq!(
+            Constraint::split_spec("openssl@major")?,
+            ("openssl", Some(Constraint::Bump(BumpKind::Major)))
+        );
+        assert_eq!(Constraint::split_spec("gcc")?, ("gcc", None));
+        assert!(Constraint::split_spec(">=20").is_err());
+        assert!(Constraint::split_spec("llvm>=").is_err());
+        assert!(Constraint::split_spec("llvm@huge").is_err());
+
+        let (_, constraint) = Constraint::split_spec("llvm>=20")?;
+        let constraint = constraint.unwrap();
+        assert!(constraint.matches("19.1.7", "20.1.0"));
+        assert!(!constraint.matches("19.1.6", "19.1.7"));
+        assert_eq!(constraint.to_string(), ">=20");
+
+        let constraint: Constraint = "@minor".parse()?;
+        assert!(constraint.matches("3.4.1", "3.5.0"));
+        assert!(!constraint.matches("3.4.1", "3.4.2"));
+
+        Ok(())
+    }
+}